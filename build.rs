@@ -0,0 +1,12 @@
+//! Generates message types and service of `proto/stte.proto` for the `grpc` feature.
+
+fn main() {
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/stte.proto");
+        // Vendored protoc, so the build does not need one installed
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("protoc for this platform");
+        std::env::set_var("PROTOC", protoc);
+        tonic_build::compile_protos("proto/stte.proto").expect("proto/stte.proto compiles");
+    }
+}
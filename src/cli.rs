@@ -0,0 +1,308 @@
+use clap::{Args, Parser, Subcommand};
+
+use crate::{
+    dialect::{Column, ColumnMapping, CsvDialect},
+    input::{InputFormat, InputOptions},
+    invariants::InvariantCheck,
+    mapping::PartnerMapping,
+    output::OutputFormat,
+    types::EngineError,
+};
+
+/// Exit code of failures not covered by other codes, e.g. rolled back inputs or differing balances.
+pub(crate) const EXIT_FAILURE: u8 = 1;
+/// Invalid arguments, also used by clap for its own errors.
+pub(crate) const EXIT_USAGE: u8 = 2;
+/// Invalid input, config, mapping, snapshot or database content, or problems found by validation.
+pub(crate) const EXIT_PARSE: u8 = 3;
+pub(crate) const EXIT_IO: u8 = 4;
+/// Client invariant violated or ledger does not balance.
+pub(crate) const EXIT_INVARIANT: u8 = 5;
+
+/// Payments engine applying deposits, withdrawals and disputes to client accounts.
+///
+/// Without command the input files are processed as by `stte process`.
+#[derive(Debug, Parser)]
+#[command(
+    name = "stte",
+    version,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+pub(crate) struct Cli {
+    #[command(subcommand)]
+    pub(crate) command: Option<Command>,
+    #[command(flatten)]
+    pub(crate) process: ProcessArgs,
+    #[command(flatten)]
+    pub(crate) engine: EngineArgs,
+}
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum Command {
+    /// Process input files and print client balances
+    Process(ProcessArgs),
+    /// Report problems of input file without processing it
+    Validate {
+        #[command(flatten)]
+        input: InputArgs,
+        file: String,
+    },
+    /// Process two inputs separately and report clients whose balances differ
+    Diff {
+        #[command(flatten)]
+        input: InputArgs,
+        left: String,
+        right: String,
+    },
+    /// Process input files and print counts of transactions and their outcomes
+    Stats {
+        #[command(flatten)]
+        input: InputArgs,
+        #[arg(required = true)]
+        files: Vec<String>,
+    },
+    /// Interactive shell, input files are processed before the first prompt
+    Repl {
+        #[command(flatten)]
+        input: InputArgs,
+        /// Append account changes as JSON lines to file, `-` for stdout
+        #[arg(long, value_name = "FILE")]
+        changes: Option<String>,
+        /// Number of last transactions which can be undone [default: unlimited]
+        #[arg(long, value_name = "COUNT")]
+        rollback_limit: Option<usize>,
+        files: Vec<String>,
+    },
+    /// HTTP service for submitting transactions and querying balances
+    Serve {
+        #[arg(long, value_name = "ADDRESS", default_value = "127.0.0.1:8080")]
+        listen: String,
+        #[command(flatten)]
+        state: StateArgs,
+    },
+    /// TCP server accepting CSV lines
+    Listen {
+        #[arg(long, value_name = "ADDRESS", default_value = "127.0.0.1:9000")]
+        listen: String,
+        #[command(flatten)]
+        state: StateArgs,
+    },
+    /// gRPC service of `proto/stte.proto`, requires `grpc` feature
+    ServeGrpc {
+        #[arg(long, value_name = "ADDRESS", default_value = "127.0.0.1:50051")]
+        listen: String,
+        #[command(flatten)]
+        state: StateArgs,
+    },
+    /// Apply transactions of Kafka topic, requires `kafka` feature
+    Consume {
+        #[arg(long, value_name = "HOST:PORT,...", default_value = "localhost:9092")]
+        brokers: String,
+        #[arg(long)]
+        topic: String,
+        #[arg(long, value_name = "ID", default_value = "stte")]
+        group: String,
+        /// Engine state and offsets saved together, replaces --database
+        #[arg(long, value_name = "FILE", conflicts_with = "database")]
+        checkpoint: String,
+        #[command(flatten)]
+        state: StateArgs,
+    },
+}
+
+/// Options of engine valid for all commands.
+#[derive(Debug, Args)]
+pub(crate) struct EngineArgs {
+    /// Engine config in TOML
+    #[arg(long, global = true, value_name = "FILE")]
+    pub(crate) config: Option<String>,
+    /// Check client invariants after each transaction or after whole input
+    #[arg(long, global = true, value_name = "each|end", value_parser = parse_invariant_check)]
+    pub(crate) check_invariants: Option<InvariantCheck>,
+}
+
+impl EngineArgs {
+    /// Command running no engine refuses engine options instead of ignoring them.
+    pub(crate) fn reject(&self, command: &str) -> Result<(), EngineError> {
+        let option = match (&self.config, &self.check_invariants) {
+            (None, None) => return Ok(()),
+            (Some(_), _) => "--config",
+            (None, Some(_)) => "--check-invariants",
+        };
+        Err(EngineError::Usage(format!(
+            "{} does not use {}",
+            command, option
+        )))
+    }
+}
+
+/// Options of engine kept running or processing input.
+#[derive(Debug, Args)]
+pub(crate) struct StateArgs {
+    /// Append account changes as JSON lines to file, `-` for stdout except with process
+    #[arg(long, value_name = "FILE")]
+    pub(crate) changes: Option<String>,
+    /// SQLite file or PostgreSQL URL persisting engine state
+    #[arg(long, value_name = "FILE|URL")]
+    pub(crate) database: Option<String>,
+    /// Number of last transactions which can be rolled back
+    #[arg(long, value_name = "COUNT", conflicts_with = "database")]
+    pub(crate) rollback_limit: Option<usize>,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct ProcessArgs {
+    #[command(flatten)]
+    pub(crate) input: InputArgs,
+    #[command(flatten)]
+    pub(crate) state: StateArgs,
+    /// Format of client balances: csv, json, jsonl or with feature parquet
+    #[arg(long, value_name = "FORMAT", default_value = "csv", value_parser = parse_output_format)]
+    pub(crate) output_format: OutputFormat,
+    /// Print ledger trial balance instead of clients
+    #[arg(long)]
+    pub(crate) trial_balance: bool,
+    /// Apply each input file all or nothing
+    #[arg(long, conflicts_with = "merge_by")]
+    pub(crate) atomic: bool,
+    /// Accept admin transactions such as `limit`, only for trusted input files
+    #[arg(long)]
+    pub(crate) admin: bool,
+    /// Processed as one stream in given order
+    #[arg(required = true)]
+    pub(crate) files: Vec<String>,
+}
+
+impl ProcessArgs {
+    /// Report is printed to stdout, so account changes must go elsewhere.
+    pub(crate) fn validate(&self) -> Result<(), EngineError> {
+        if self.state.changes.as_deref() == Some("-") {
+            return Err(EngineError::Usage(
+                "--changes - would mix account changes with the report on stdout, use a file"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Format and CSV dialect of input files.
+#[derive(Debug, Args)]
+pub(crate) struct InputArgs {
+    /// Format csv, json, jsonl or with features arrow and parquet, detected from file extension when not set
+    #[arg(long, value_name = "FORMAT", value_parser = parse_input_format)]
+    input_format: Option<InputFormat>,
+    /// Field delimiter of CSV input [default: ,]
+    #[arg(long, value_name = "CHAR", value_parser = |value: &str| parse_char("delimiter", value))]
+    delimiter: Option<u8>,
+    /// Quote character of CSV input [default: "]
+    #[arg(long, value_name = "CHAR", value_parser = |value: &str| parse_char("quote", value))]
+    quote: Option<u8>,
+    /// Lines starting with the character are skipped
+    #[arg(long, value_name = "CHAR", value_parser = |value: &str| parse_char("comment", value))]
+    comment: Option<u8>,
+    /// Input has no header line
+    #[arg(long)]
+    no_header: bool,
+    /// Columns of type, client, tx and amount by name or index
+    #[arg(long, value_name = "TYPE,CLIENT,TX,AMOUNT", value_parser = parse_columns)]
+    columns: Option<ColumnMapping>,
+    /// Partner mapping file, used with --partner
+    #[arg(long, value_name = "FILE", requires = "partner")]
+    mapping: Option<String>,
+    /// Partner of mapping file whose schema the input has
+    #[arg(long, value_name = "NAME", requires = "mapping")]
+    partner: Option<String>,
+    /// Merge multiple CSV inputs ordered by this column
+    #[arg(long, value_name = "COLUMN")]
+    merge_by: Option<String>,
+}
+
+impl InputArgs {
+    /// Input options with partner mapping applied.
+    pub(crate) fn options(&self) -> Result<InputOptions, EngineError> {
+        let mut options = InputOptions {
+            format: self.input_format,
+            merge_by: self.merge_by.as_deref().map(Column::parse),
+            ..Default::default()
+        };
+        let dialect = &mut options.dialect;
+        dialect.delimiter = self.delimiter.unwrap_or(dialect.delimiter);
+        dialect.quote = self.quote.unwrap_or(dialect.quote);
+        dialect.comment = self.comment;
+        dialect.has_headers = !self.no_header;
+        dialect.columns = self.columns.clone();
+        dialect.validate()?;
+
+        if let (Some(mapping), Some(partner)) = (&self.mapping, &self.partner) {
+            PartnerMapping::from_path(mapping, partner)?.apply(dialect)?;
+        }
+        Ok(options)
+    }
+}
+
+fn parse_input_format(format: &str) -> Result<InputFormat, String> {
+    InputFormat::parse(format).ok_or_else(|| "unsupported input format".to_string())
+}
+
+fn parse_output_format(format: &str) -> Result<OutputFormat, String> {
+    OutputFormat::parse(format).ok_or_else(|| "unsupported output format".to_string())
+}
+
+fn parse_invariant_check(check: &str) -> Result<InvariantCheck, String> {
+    match check {
+        "each" => Ok(InvariantCheck::Each),
+        "end" => Ok(InvariantCheck::End),
+        _ => Err("must be each or end".to_string()),
+    }
+}
+
+fn parse_char(name: &str, value: &str) -> Result<u8, String> {
+    CsvDialect::parse_char(name, value).map_err(|err| err.to_string())
+}
+
+fn parse_columns(columns: &str) -> Result<ColumnMapping, String> {
+    ColumnMapping::parse(columns).map_err(|err| err.to_string())
+}
+
+/// Exit code of failed command by class of the error.
+pub(crate) fn exit_code(err: &EngineError) -> u8 {
+    match err {
+        EngineError::InFile { source, .. } => exit_code(source),
+        EngineError::Io(_) => EXIT_IO,
+        EngineError::InvalidInput(err) if err.is_io_error() => EXIT_IO,
+        EngineError::InvalidInput(_)
+        | EngineError::UnknownColumn { .. }
+        | EngineError::MissingColumn { .. }
+        | EngineError::BadTypeToken { .. }
+        | EngineError::NonNumericClient { .. }
+        | EngineError::NonIntegerTx { .. }
+        | EngineError::BadDecimal { .. }
+        | EngineError::InvalidJsonInput(_)
+        | EngineError::InvalidJsonLine { .. }
+        | EngineError::InvalidJsonElement { .. }
+        | EngineError::AmountMissing(_)
+        | EngineError::AmountNotPositive(_)
+        | EngineError::AmountNegative(_)
+        | EngineError::AdminTransaction(_)
+        | EngineError::InvalidMerge(_)
+        | EngineError::InvalidMapping(_)
+        | EngineError::ConfigParse(_)
+        | EngineError::InvalidConfig(_)
+        | EngineError::InvalidSnapshot(_)
+        | EngineError::InvalidDatabase(_)
+        | EngineError::ValidationFailed(_) => EXIT_PARSE,
+        #[cfg(feature = "arrow")]
+        EngineError::ArrowData(_) => EXIT_PARSE,
+        #[cfg(feature = "parquet")]
+        EngineError::ParquetData(_) => EXIT_PARSE,
+        EngineError::InvalidDialect(_) | EngineError::Usage(_) => EXIT_USAGE,
+        EngineError::InvariantViolated(_) | EngineError::LedgerImbalance(_) => EXIT_INVARIANT,
+        _ => EXIT_FAILURE,
+    }
+}
+
+#[cfg(test)]
+#[path = "cli.test.rs"]
+mod tests;
@@ -0,0 +1,156 @@
+use clap::{error::ErrorKind, CommandFactory, Parser};
+
+use crate::{
+    cli::{exit_code, Cli, Command, EXIT_FAILURE, EXIT_INVARIANT, EXIT_IO, EXIT_PARSE, EXIT_USAGE},
+    dialect::Column,
+    invariants::InvariantCheck,
+    types::EngineError,
+};
+
+fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+    Cli::try_parse_from(std::iter::once("stte").chain(args.iter().copied()))
+}
+
+#[test]
+fn test_cli_is_valid() {
+    Cli::command().debug_assert();
+}
+
+#[test]
+fn test_files_without_command_are_processed() {
+    let cli = parse(&["--merge-by", "tx", "a.csv", "b.csv"]).unwrap();
+
+    assert!(cli.command.is_none());
+    assert_eq!(cli.process.files, vec!["a.csv", "b.csv"]);
+    let options = cli.process.input.options().unwrap();
+    assert_eq!(options.merge_by, Some(Column::Name("tx".to_string())));
+}
+
+#[test]
+fn test_global_options_after_command() {
+    let cli = parse(&[
+        "stats",
+        "a.csv",
+        "--check-invariants",
+        "end",
+        "--config",
+        "c.toml",
+    ])
+    .unwrap();
+
+    assert!(matches!(cli.command, Some(Command::Stats { files, .. }) if files == ["a.csv"]));
+    assert_eq!(cli.engine.check_invariants, Some(InvariantCheck::End));
+    assert_eq!(cli.engine.config.as_deref(), Some("c.toml"));
+}
+
+#[test]
+fn test_serve_default_address() {
+    let cli = parse(&["serve"]).unwrap();
+
+    assert!(
+        matches!(cli.command, Some(Command::Serve { listen, .. }) if listen == "127.0.0.1:8080")
+    );
+}
+
+#[test]
+fn test_usage_errors() {
+    for args in [
+        &[][..],
+        &["--output-format", "xml", "a.csv"],
+        &["--atomic", "--merge-by", "tx", "a.csv"],
+        &["--rollback-limit", "5", "--database", "stte.db", "a.csv"],
+        &["--mapping", "partners.toml", "a.csv"],
+        &["--delimiter", "ab", "a.csv"],
+        &["serve", "a.csv"],
+        &["validate", "a.csv", "b.csv"],
+        &["diff", "a.csv"],
+        &["consume", "--topic", "transactions"],
+        &[
+            "consume",
+            "--topic",
+            "transactions",
+            "--checkpoint",
+            "state.json",
+            "--database",
+            "stte.db",
+        ],
+        &["repl", "--database", "stte.db"],
+    ] {
+        let err = parse(args).unwrap_err();
+        assert_eq!(err.exit_code(), i32::from(EXIT_USAGE), "{:?}", args);
+    }
+}
+
+#[test]
+fn test_process_changes_not_on_stdout() {
+    let cli = parse(&["--changes", "-", "a.csv"]).unwrap();
+    let err = cli.process.validate().unwrap_err();
+    assert!(matches!(err, EngineError::Usage(_)));
+    assert_eq!(exit_code(&err), EXIT_USAGE);
+
+    let cli = parse(&["process", "--changes", "changes.jsonl", "a.csv"]).unwrap();
+    assert!(matches!(cli.command, Some(Command::Process(args)) if args.validate().is_ok()));
+}
+
+#[test]
+fn test_validate_rejects_engine_options() {
+    for args in [
+        &["validate", "--config", "missing.toml", "a.csv"][..],
+        &["validate", "a.csv", "--check-invariants", "each"],
+    ] {
+        let cli = parse(args).unwrap();
+        let err = cli.engine.reject("validate").unwrap_err();
+        assert!(matches!(err, EngineError::Usage(_)), "{:?}", args);
+        assert_eq!(exit_code(&err), EXIT_USAGE);
+    }
+
+    let cli = parse(&["validate", "a.csv"]).unwrap();
+    assert!(cli.engine.reject("validate").is_ok());
+}
+
+#[test]
+fn test_help_is_not_error() {
+    let err = parse(&["--help"]).unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::DisplayHelp);
+    assert_eq!(err.exit_code(), 0);
+}
+
+#[test]
+fn test_exit_codes() {
+    let io = || EngineError::Io(std::io::Error::other("disk"));
+    let in_file = |source| EngineError::InFile {
+        filename: "a.csv".to_string(),
+        source: Box::new(source),
+    };
+
+    assert_eq!(exit_code(&io()), EXIT_IO);
+    assert_eq!(exit_code(&in_file(io())), EXIT_IO);
+    assert_eq!(exit_code(&EngineError::AmountMissing(1)), EXIT_PARSE);
+    assert_eq!(
+        exit_code(&in_file(EngineError::AmountMissing(1))),
+        EXIT_PARSE
+    );
+    for err in [
+        EngineError::InvalidConfig("fees".to_string()),
+        EngineError::InvalidSnapshot("version".to_string()),
+        EngineError::InvalidDatabase("amount".to_string()),
+        EngineError::ValidationFailed(2),
+    ] {
+        assert_eq!(exit_code(&err), EXIT_PARSE, "{}", err);
+    }
+    assert_eq!(
+        exit_code(&EngineError::InvalidDialect("quote".to_string())),
+        EXIT_USAGE
+    );
+    assert_eq!(
+        exit_code(&EngineError::InvariantViolated("client 1".to_string())),
+        EXIT_INVARIANT
+    );
+    assert_eq!(
+        exit_code(&EngineError::LedgerImbalance("client 1".to_string())),
+        EXIT_INVARIANT
+    );
+    assert_eq!(exit_code(&EngineError::RolledBack(1)), EXIT_FAILURE);
+    assert_eq!(exit_code(&EngineError::BalancesDiffer(2)), EXIT_FAILURE);
+}
@@ -0,0 +1,218 @@
+use std::{fs::File, str::FromStr};
+
+use arrow::{
+    array::{Array, ArrayRef, AsArray, RecordBatch},
+    compute::{cast_with_options, CastOptions},
+    datatypes::{DataType, UInt16Type, UInt32Type},
+    error::ArrowError,
+};
+use rust_decimal::Decimal;
+use serde::{de::IntoDeserializer, Deserialize};
+
+use crate::{
+    input::Transactions,
+    types::{EngineError, Transaction, TransactionType},
+};
+
+/// Transactions of Arrow IPC file, read batch by batch.
+pub(crate) fn read_arrow_ipc(file: File) -> Result<Transactions, EngineError> {
+    let reader = arrow::ipc::reader::FileReader::try_new(file, None)?;
+    Ok(transactions_from_batches(reader))
+}
+
+/// Transactions of Parquet file, read batch by batch.
+#[cfg(feature = "parquet")]
+pub(crate) fn read_parquet(file: File) -> Result<Transactions, EngineError> {
+    let reader =
+        parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder::try_new(file)?.build()?;
+    Ok(transactions_from_batches(reader))
+}
+
+fn transactions_from_batches<I>(batches: I) -> Transactions
+where
+    I: Iterator<Item = Result<RecordBatch, ArrowError>> + 'static,
+{
+    Box::new(batches.flat_map(|batch| {
+        match batch
+            .map_err(EngineError::from)
+            .and_then(|batch| batch_transactions(&batch))
+        {
+            Ok(transactions) => transactions.into_iter().map(Ok).collect(),
+            Err(err) => vec![Err(err)],
+        }
+    }))
+}
+
+/// Convert columns `type`, `client`, `tx` and `amount` of batch, other columns are ignored.
+///
+/// Columns are cast to expected types, so e.g. `client` can be any integer type in range of client id
+/// and `amount` can be a decimal or a string.
+pub(crate) fn batch_transactions(batch: &RecordBatch) -> Result<Vec<Transaction>, EngineError> {
+    let tx_types = cast_column(batch, "type", &DataType::Utf8)?;
+    let clients = cast_column(batch, "client", &DataType::UInt16)?;
+    let txs = cast_column(batch, "tx", &DataType::UInt32)?;
+    let amounts = cast_column(batch, "amount", &DataType::Utf8)?;
+
+    let tx_types = tx_types.as_string::<i32>();
+    let clients = clients.as_primitive::<UInt16Type>();
+    let txs = txs.as_primitive::<UInt32Type>();
+    let amounts = amounts.as_string::<i32>();
+
+    (0..batch.num_rows())
+        .map(|row| {
+            if tx_types.is_null(row) || clients.is_null(row) || txs.is_null(row) {
+                return Err(ArrowError::InvalidArgumentError(format!(
+                    "null type, client or tx in row {}",
+                    row
+                ))
+                .into());
+            }
+
+            let tx_type = TransactionType::deserialize(tx_types.value(row).into_deserializer())
+                .map_err(|err: serde::de::value::Error| {
+                    ArrowError::InvalidArgumentError(format!("row {}: {}", row, err))
+                })?;
+            let amount = match amounts.is_null(row) {
+                true => None,
+                false => Some(Decimal::from_str(amounts.value(row)).map_err(|err| {
+                    ArrowError::InvalidArgumentError(format!("row {}: {}", row, err))
+                })?),
+            };
+
+            Ok(Transaction {
+                tx_type,
+                client: clients.value(row),
+                tx: txs.value(row),
+                amount,
+            })
+        })
+        .collect()
+}
+
+/// Cast column failing on values out of range instead of turning them into nulls.
+fn cast_column(
+    batch: &RecordBatch,
+    name: &str,
+    to_type: &DataType,
+) -> Result<ArrayRef, EngineError> {
+    let column = batch
+        .column_by_name(name)
+        .ok_or_else(|| ArrowError::SchemaError(format!("missing column {}", name)))?;
+
+    let options = CastOptions {
+        safe: false,
+        ..Default::default()
+    };
+    Ok(cast_with_options(column, to_type, &options)?)
+}
+
+#[cfg(feature = "parquet")]
+pub(crate) use parquet_output::write_parquet;
+
+#[cfg(feature = "parquet")]
+mod parquet_output {
+    use std::{io::Write, sync::Arc};
+
+    use arrow::{
+        array::{BooleanArray, Decimal128Array, RecordBatch, UInt16Array, UInt64Array},
+        datatypes::{DataType, Field, Schema},
+    };
+    use parquet::arrow::ArrowWriter;
+    use rust_decimal::Decimal;
+
+    use crate::{
+        output::ClientBalance,
+        types::{Amount, EngineError},
+    };
+
+    /// Maximal precision of Arrow `Decimal128`
+    const PRECISION: u8 = 38;
+
+    /// Write client balances as Parquet with amounts as `Decimal128` of scale large enough for all amounts.
+    pub(crate) fn write_parquet<W: Write + Send>(
+        balances: &[ClientBalance],
+        writer: W,
+    ) -> Result<(), EngineError> {
+        let amounts = balances.iter().flat_map(|balance| {
+            [balance.available, balance.held, balance.total]
+                .into_iter()
+                .chain(balance.fees)
+        });
+        let scale = amounts.map(|amount| amount.scale()).max().unwrap_or(0);
+
+        let decimals = |amounts: Vec<Amount>| {
+            Decimal128Array::from_iter_values(amounts.into_iter().map(|mut amount| {
+                amount.rescale(scale);
+                amount.mantissa()
+            }))
+            .with_precision_and_scale(PRECISION, scale as i8)
+        };
+        let decimal_type = DataType::Decimal128(PRECISION, scale as i8);
+
+        let mut fields = vec![
+            Field::new("client", DataType::UInt16, false),
+            Field::new("available", decimal_type.clone(), false),
+            Field::new("held", decimal_type.clone(), false),
+            Field::new("total", decimal_type.clone(), false),
+            Field::new("locked", DataType::Boolean, false),
+            Field::new("open_disputes", DataType::UInt64, false),
+        ];
+        let mut columns: Vec<Arc<dyn arrow::array::Array>> = vec![
+            Arc::new(UInt16Array::from_iter_values(
+                balances.iter().map(|balance| balance.client),
+            )),
+            Arc::new(decimals(
+                balances.iter().map(|balance| balance.available).collect(),
+            )?),
+            Arc::new(decimals(
+                balances.iter().map(|balance| balance.held).collect(),
+            )?),
+            Arc::new(decimals(
+                balances.iter().map(|balance| balance.total).collect(),
+            )?),
+            Arc::new(BooleanArray::from_iter(
+                balances.iter().map(|balance| Some(balance.locked)),
+            )),
+            Arc::new(UInt64Array::from_iter_values(
+                balances.iter().map(|balance| balance.open_disputes as u64),
+            )),
+        ];
+
+        if balances
+            .first()
+            .is_some_and(|balance| balance.limit_breaches.is_some())
+        {
+            fields.push(Field::new("limit_breaches", DataType::UInt64, false));
+            columns.push(Arc::new(UInt64Array::from_iter_values(
+                balances
+                    .iter()
+                    .map(|balance| balance.limit_breaches.unwrap_or(0) as u64),
+            )));
+        }
+        if balances
+            .first()
+            .is_some_and(|balance| balance.fees.is_some())
+        {
+            fields.push(Field::new("fees", decimal_type, false));
+            columns.push(Arc::new(decimals(
+                balances
+                    .iter()
+                    .map(|balance| balance.fees.unwrap_or(Decimal::ZERO))
+                    .collect(),
+            )?));
+        }
+
+        let schema = Arc::new(Schema::new(fields));
+        let batch = RecordBatch::try_new(schema.clone(), columns)?;
+
+        let mut writer = ArrowWriter::try_new(writer, schema, None)?;
+        writer.write(&batch)?;
+        writer.close()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[path = "columnar.test.rs"]
+mod tests;
@@ -0,0 +1,101 @@
+use std::sync::Arc;
+
+use arrow::{
+    array::{Decimal128Array, Int64Array, RecordBatch, StringArray},
+    datatypes::{DataType, Field, Schema},
+};
+use rust_decimal_macros::dec;
+
+use crate::{
+    columnar::batch_transactions,
+    types::{Transaction, TransactionType},
+};
+
+fn batch(clients: Int64Array) -> RecordBatch {
+    let schema = Schema::new(vec![
+        Field::new("type", DataType::Utf8, false),
+        Field::new("client", DataType::Int64, false),
+        Field::new("tx", DataType::Int64, false),
+        Field::new("amount", DataType::Decimal128(10, 2), true),
+    ]);
+
+    RecordBatch::try_new(
+        Arc::new(schema),
+        vec![
+            Arc::new(StringArray::from(vec!["deposit", "dispute"])),
+            Arc::new(clients),
+            Arc::new(Int64Array::from(vec![1, 1])),
+            Arc::new(
+                Decimal128Array::from(vec![Some(150), None])
+                    .with_precision_and_scale(10, 2)
+                    .unwrap(),
+            ),
+        ],
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_batch_transactions() {
+    assert_eq!(
+        batch_transactions(&batch(Int64Array::from(vec![1, 1]))).unwrap(),
+        vec![
+            Transaction {
+                tx_type: TransactionType::Deposit,
+                client: 1,
+                tx: 1,
+                amount: Some(dec!(1.50)),
+            },
+            Transaction {
+                tx_type: TransactionType::Dispute,
+                client: 1,
+                tx: 1,
+                amount: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_client_out_of_range() {
+    assert!(batch_transactions(&batch(Int64Array::from(vec![1, 65536]))).is_err());
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn test_write_parquet() {
+    use arrow::array::{Array, AsArray};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    use crate::{columnar::write_parquet, output::ClientBalance};
+
+    let balances = [ClientBalance {
+        client: 1,
+        available: dec!(1.5),
+        held: dec!(0.0001),
+        total: dec!(1.5001),
+        locked: false,
+        open_disputes: 1,
+        limit_breaches: None,
+        fees: None,
+    }];
+
+    let mut parquet = Vec::new();
+    write_parquet(&balances, &mut parquet).unwrap();
+
+    let batch = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(parquet))
+        .unwrap()
+        .build()
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(batch.num_columns(), 6);
+    let available = batch
+        .column_by_name("available")
+        .unwrap()
+        .as_primitive::<arrow::datatypes::Decimal128Type>();
+    assert_eq!(available.data_type(), &DataType::Decimal128(38, 4));
+    assert_eq!(available.value(0), 15000);
+}
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+
+use rust_decimal::Decimal;
+use serde::{de::Error, Deserialize, Deserializer};
+
+use crate::{
+    fees::FeeSchedule,
+    limits::WithdrawalLimits,
+    risk::RiskRulesConfig,
+    types::{Amount, ClientId, EngineError},
+};
+
+/// Per-client settings. Loaded from config file, can be changed by admin transactions.
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ClientConfig {
+    /// Withdrawals are allowed down to `-credit_limit` available amount.
+    #[serde(default, with = "rust_decimal::serde::str")]
+    pub(crate) credit_limit: Amount,
+    /// Overrides of global withdrawal limits
+    #[serde(default)]
+    pub(crate) withdrawal_limits: WithdrawalLimits,
+    /// Overrides of global fees
+    #[serde(default)]
+    pub(crate) fees: FeeSchedule,
+}
+
+/// Engine configuration, e.g.:
+///
+/// ```toml
+/// [withdrawal_limits]
+/// max_amount = "1000.0"
+/// max_count = 3
+/// window = 100
+///
+/// [fees]
+/// withdrawal_flat = "0.5"
+/// deposit_percent = "1.0"
+///
+/// [risk_rules]
+/// lock_on_open_disputes = 3
+///
+/// [clients.1]
+/// credit_limit = "100.0"
+/// withdrawal_limits = { max_amount = "5000.0" }
+/// fees = { withdrawal_flat = "0" }
+/// ```
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct EngineConfig {
+    /// Global withdrawal limits
+    #[serde(default)]
+    pub(crate) withdrawal_limits: WithdrawalLimits,
+    /// Global fees
+    #[serde(default)]
+    pub(crate) fees: FeeSchedule,
+    /// Built-in risk rules
+    #[serde(default)]
+    pub(crate) risk_rules: RiskRulesConfig,
+    #[serde(default, deserialize_with = "deserialize_clients")]
+    pub(crate) clients: HashMap<ClientId, ClientConfig>,
+}
+
+/// TOML table keys are always strings, parse them into client ids explicitly.
+fn deserialize_clients<'de, D>(deserializer: D) -> Result<HashMap<ClientId, ClientConfig>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    HashMap::<String, ClientConfig>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(client_id, client_config)| {
+            client_id
+                .parse()
+                .map(|client_id| (client_id, client_config))
+                .map_err(|_| D::Error::custom(format!("invalid client id: {}", client_id)))
+        })
+        .collect()
+}
+
+impl EngineConfig {
+    pub(crate) fn from_path(filename: &str) -> Result<Self, EngineError> {
+        Self::from_toml(&std::fs::read_to_string(filename)?)
+    }
+
+    /// Parse and validate config, fees of clients are validated together with global ones they fall back to.
+    pub(crate) fn from_toml(content: &str) -> Result<Self, EngineError> {
+        let config: Self = toml::from_str(content)?;
+
+        validate_withdrawal_limits(&config.withdrawal_limits, "global")?;
+        validate_fees(&config.fees, "global")?;
+
+        if config.risk_rules.lock_on_open_disputes == Some(0) {
+            return Err(EngineError::InvalidConfig(
+                "lock_on_open_disputes must not be zero".to_string(),
+            ));
+        }
+
+        for (client_id, client_config) in &config.clients {
+            if client_config.credit_limit < Decimal::ZERO {
+                return Err(EngineError::InvalidConfig(format!(
+                    "negative credit limit for client {}",
+                    client_id
+                )));
+            }
+            validate_withdrawal_limits(
+                &client_config.withdrawal_limits,
+                &format!("client {}", client_id),
+            )?;
+            validate_fees(
+                &client_config.fees.or(&config.fees),
+                &format!("client {}", client_id),
+            )?;
+        }
+
+        Ok(config)
+    }
+
+    pub(crate) fn credit_limit(&self, client_id: ClientId) -> Amount {
+        self.clients
+            .get(&client_id)
+            .map_or(Decimal::ZERO, |client_config| client_config.credit_limit)
+    }
+
+    /// Effective withdrawal limits of client, falling back to global ones.
+    pub(crate) fn withdrawal_limits(&self, client_id: ClientId) -> WithdrawalLimits {
+        match self.clients.get(&client_id) {
+            Some(client_config) => client_config.withdrawal_limits.or(&self.withdrawal_limits),
+            None => self.withdrawal_limits.clone(),
+        }
+    }
+
+    /// Effective fees of client, falling back to global ones.
+    pub(crate) fn fees(&self, client_id: ClientId) -> FeeSchedule {
+        match self.clients.get(&client_id) {
+            Some(client_config) => client_config.fees.or(&self.fees),
+            None => self.fees.clone(),
+        }
+    }
+
+    pub(crate) fn has_fees(&self) -> bool {
+        !self.fees.is_empty()
+            || self
+                .clients
+                .values()
+                .any(|client_config| !client_config.fees.is_empty())
+    }
+
+    pub(crate) fn has_withdrawal_limits(&self) -> bool {
+        !self.withdrawal_limits.is_empty()
+            || self
+                .clients
+                .values()
+                .any(|client_config| !client_config.withdrawal_limits.is_empty())
+    }
+}
+
+fn validate_withdrawal_limits(limits: &WithdrawalLimits, scope: &str) -> Result<(), EngineError> {
+    if limits
+        .max_amount
+        .is_some_and(|amount| amount <= Decimal::ZERO)
+        || limits
+            .max_total
+            .is_some_and(|amount| amount <= Decimal::ZERO)
+    {
+        return Err(EngineError::InvalidConfig(format!(
+            "withdrawal amount limits must be positive ({})",
+            scope
+        )));
+    }
+    if limits.window == Some(0) {
+        return Err(EngineError::InvalidConfig(format!(
+            "withdrawal limits window must not be zero ({})",
+            scope
+        )));
+    }
+    Ok(())
+}
+
+fn validate_fees(fees: &FeeSchedule, scope: &str) -> Result<(), EngineError> {
+    let amounts = [
+        fees.withdrawal_flat,
+        fees.deposit_percent,
+        fees.deposit_min,
+        fees.deposit_cap,
+    ];
+    if amounts
+        .into_iter()
+        .flatten()
+        .any(|amount| amount < Decimal::ZERO)
+    {
+        return Err(EngineError::InvalidConfig(format!(
+            "fees must not be negative ({})",
+            scope
+        )));
+    }
+    if let (Some(min), Some(cap)) = (fees.deposit_min, fees.deposit_cap) {
+        if min > cap {
+            return Err(EngineError::InvalidConfig(format!(
+                "deposit_min must not be greater than deposit_cap ({})",
+                scope
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[path = "config.test.rs"]
+mod tests;
@@ -0,0 +1,43 @@
+use crate::{config::EngineConfig, types::EngineError};
+
+#[test]
+fn test_deposit_min_above_cap() {
+    let err = EngineConfig::from_toml(
+        r#"
+        [fees]
+        deposit_percent = "1"
+        deposit_min = "5"
+        deposit_cap = "2"
+        "#,
+    )
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        EngineError::InvalidConfig(message) if message == "deposit_min must not be greater than deposit_cap (global)"
+    ));
+
+    // Client minimum is checked against global cap it falls back to
+    let err = EngineConfig::from_toml(
+        r#"
+        [fees]
+        deposit_percent = "1"
+        deposit_cap = "2"
+
+        [clients.3]
+        fees = { deposit_min = "5" }
+        "#,
+    )
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        EngineError::InvalidConfig(message) if message == "deposit_min must not be greater than deposit_cap (client 3)"
+    ));
+}
+
+#[test]
+fn test_negative_fee() {
+    assert!(matches!(
+        EngineConfig::from_toml("[fees]\nwithdrawal_flat = \"-1\""),
+        Err(EngineError::InvalidConfig(_))
+    ));
+}
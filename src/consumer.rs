@@ -0,0 +1,419 @@
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    fs::{File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use rdkafka::{
+    consumer::{BaseConsumer, CommitMode, Consumer as _},
+    error::{KafkaError, RDKafkaErrorCode},
+    ClientConfig, Message as _, Offset, TopicPartitionList,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    engine::Engine,
+    input,
+    snapshot::{self, Snapshot},
+    types::{EngineError, TransactionOutcome},
+};
+
+/// Message of partitioned log, payload is transaction as JSON object.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Record {
+    pub(crate) partition: i32,
+    pub(crate) offset: i64,
+    pub(crate) payload: Vec<u8>,
+}
+
+/// Partitioned message log with committed offsets, e.g. Kafka topic.
+pub(crate) trait MessageLog {
+    /// Read all partitions, partitions in `offsets` from given offset, other ones from the beginning.
+    fn assign(&mut self, offsets: &BTreeMap<i32, i64>) -> Result<(), EngineError>;
+
+    /// Next record, `None` when there is no record available now.
+    fn poll(&mut self) -> Result<Option<Record>, EngineError>;
+
+    /// Commit offsets of next records to read.
+    fn commit(&mut self, offsets: &BTreeMap<i32, i64>) -> Result<(), EngineError>;
+}
+
+/// Number of records applied between checkpoints at most.
+const BATCH_SIZE: usize = 1000;
+/// Checkpoints appended to checkpoint log before it is compacted into full checkpoint.
+const COMPACT_AFTER: u64 = 100;
+
+/// Consume Kafka topic until the process is stopped, see [`Consumer`].
+pub(crate) fn consume(
+    engine: &mut Engine,
+    brokers: &str,
+    topic: &str,
+    group: &str,
+    checkpoint: &str,
+) -> Result<(), EngineError> {
+    let log = KafkaLog::connect(brokers, topic, group)?;
+    let mut consumer = Consumer::start(log, engine, checkpoint.into(), BATCH_SIZE, COMPACT_AFTER)?;
+    eprintln!("Consuming topic {} from {}", topic, brokers);
+
+    loop {
+        consumer.poll(engine)?;
+    }
+}
+
+/// Engine state together with offsets of next records to read, saved atomically.
+#[derive(Debug, Serialize, Deserialize)]
+struct Checkpoint {
+    offsets: BTreeMap<i32, i64>,
+    engine: Snapshot,
+    /// Number of the last [`Increment`] included in the state, older ones in checkpoint log are skipped
+    #[serde(default)]
+    increments: u64,
+}
+
+/// Transactions applied since previous checkpoint with offsets after them, one JSON line of checkpoint log.
+#[derive(Debug, Serialize, Deserialize)]
+struct Increment {
+    number: u64,
+    offsets: BTreeMap<i32, i64>,
+    transactions: Vec<serde_json::Value>,
+}
+
+impl Checkpoint {
+    fn load(path: &Path) -> Result<Option<Self>, EngineError> {
+        match std::fs::read(path) {
+            Ok(content) => serde_json::from_slice(&content)
+                .map(Some)
+                .map_err(|err| EngineError::InvalidSnapshot(err.to_string())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn save(&self, path: &Path) -> Result<(), EngineError> {
+        let content = serde_json::to_vec(self).expect("checkpoint is serializable");
+        Ok(snapshot::write_atomic(path, &content)?)
+    }
+}
+
+impl Increment {
+    /// Complete lines of checkpoint log, torn last line of interrupted append is cut off.
+    fn load(path: &Path) -> Result<Vec<Self>, EngineError> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let complete = content.rfind('\n').map_or(0, |end| end + 1);
+        if complete < content.len() {
+            OpenOptions::new()
+                .write(true)
+                .open(path)?
+                .set_len(complete as u64)?;
+        }
+        content[..complete]
+            .lines()
+            .map(|line| {
+                serde_json::from_str(line)
+                    .map_err(|err| EngineError::InvalidSnapshot(err.to_string()))
+            })
+            .collect()
+    }
+
+    fn append(&self, path: &Path) -> Result<(), EngineError> {
+        let mut line = serde_json::to_vec(self).expect("increment is serializable");
+        line.push(b'\n');
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        file.write_all(&line)?;
+        Ok(file.sync_data()?)
+    }
+}
+
+/// Checkpoint log next to checkpoint file, e.g. `state.json.log`.
+pub(crate) fn log_path(checkpoint: &Path) -> PathBuf {
+    let mut path = OsString::from(checkpoint.as_os_str());
+    path.push(".log");
+    path.into()
+}
+
+/// Applies transactions of message log to engine exactly once, also across restarts.
+///
+/// Offsets are committed to the log only after engine state and the offsets are saved to checkpoint file.
+/// On start the engine is restored from the checkpoint and the log is read from offsets saved with it,
+/// so transactions applied after the last checkpoint are applied again and no other ones.
+///
+/// Checkpoint appends only transactions applied since the previous one to checkpoint log, full engine state
+/// is written every `compact_after` checkpoints and the log is emptied.
+pub(crate) struct Consumer<L> {
+    log: L,
+    checkpoint: PathBuf,
+    /// Offsets of next records to read by partition
+    offsets: BTreeMap<i32, i64>,
+    /// Records applied since last checkpoint
+    pending: usize,
+    /// Transactions applied since last checkpoint as read from records
+    transactions: Vec<serde_json::Value>,
+    /// Number of the last checkpoint and of the last full one
+    increments: u64,
+    compacted: u64,
+    /// Maximum number of records applied between checkpoints
+    batch_size: usize,
+    compact_after: u64,
+}
+
+impl<L: MessageLog> Consumer<L> {
+    /// Restore engine from checkpoint if it exists and start reading the log after it.
+    pub(crate) fn start(
+        mut log: L,
+        engine: &mut Engine,
+        checkpoint: PathBuf,
+        batch_size: usize,
+        compact_after: u64,
+    ) -> Result<Self, EngineError> {
+        let (mut offsets, compacted) = match Checkpoint::load(&checkpoint)? {
+            Some(saved) => {
+                engine.restore(saved.engine)?;
+                (saved.offsets, saved.increments)
+            }
+            None => (BTreeMap::new(), 0),
+        };
+
+        let mut increments = compacted;
+        for increment in Increment::load(&log_path(&checkpoint))? {
+            if increment.number <= compacted {
+                continue;
+            }
+            engine.replay(
+                increment
+                    .transactions
+                    .into_iter()
+                    .filter_map(|value| input::parse_json(&value, 1).ok()),
+            );
+            offsets = increment.offsets;
+            increments = increment.number;
+        }
+        log.assign(&offsets)?;
+
+        Ok(Self {
+            log,
+            checkpoint,
+            offsets,
+            pending: 0,
+            transactions: Vec::new(),
+            increments,
+            compacted,
+            batch_size: batch_size.max(1),
+            compact_after: compact_after.max(1),
+        })
+    }
+
+    /// Apply records available now, checkpoint after each `batch_size` records and when there is no next record.
+    ///
+    /// Invalid records are reported to stderr and skipped. Returns number of consumed records.
+    pub(crate) fn poll(&mut self, engine: &mut Engine) -> Result<usize, EngineError> {
+        let mut consumed = 0;
+        while let Some(record) = self.log.poll()? {
+            self.apply(engine, record);
+            consumed += 1;
+            if self.pending >= self.batch_size {
+                self.checkpoint(engine)?;
+            }
+        }
+        if self.pending > 0 {
+            self.checkpoint(engine)?;
+        }
+
+        Ok(consumed)
+    }
+
+    fn apply(&mut self, engine: &mut Engine, record: Record) {
+        let outcome = serde_json::from_slice::<serde_json::Value>(&record.payload)
+            .map_err(EngineError::from)
+            .and_then(|value| {
+                let transaction = input::parse_json(&value, 1)?;
+                self.transactions.push(value);
+                Ok(transaction)
+            })
+            .and_then(|transaction| engine.process_transaction(transaction));
+        match outcome {
+            Ok(TransactionOutcome::Applied | TransactionOutcome::Rejected(_)) => {}
+            Err(err) => eprintln!(
+                "Skipping record {} of partition {}: {}",
+                record.offset, record.partition, err
+            ),
+        }
+
+        self.offsets.insert(record.partition, record.offset + 1);
+        self.pending += 1;
+    }
+
+    fn checkpoint(&mut self, engine: &Engine) -> Result<(), EngineError> {
+        let number = self.increments + 1;
+        let log_path = log_path(&self.checkpoint);
+        if number - self.compacted >= self.compact_after {
+            Checkpoint {
+                offsets: self.offsets.clone(),
+                engine: engine.snapshot(),
+                increments: number,
+            }
+            .save(&self.checkpoint)?;
+            // Lines of the log are older than the checkpoint now and skipped if truncation is interrupted
+            File::create(&log_path)?;
+            self.compacted = number;
+        } else {
+            Increment {
+                number,
+                offsets: self.offsets.clone(),
+                transactions: std::mem::take(&mut self.transactions),
+            }
+            .append(&log_path)?;
+        }
+        self.increments = number;
+        self.transactions.clear();
+        self.pending = 0;
+
+        if let Err(err) = self.log.commit(&self.offsets) {
+            // Committed offsets only lag behind, checkpoint decides where reading continues on restart
+            eprintln!("Error committing offsets: {}", err);
+        }
+
+        Ok(())
+    }
+}
+
+/// Kafka topic read with manually assigned partitions, offsets are committed for consumer group.
+pub(crate) struct KafkaLog {
+    consumer: BaseConsumer,
+    topic: String,
+    timeout: Duration,
+    backoff: Backoff,
+}
+
+/// Delay before retrying after consecutive errors, doubled after each error up to `max`.
+#[derive(Debug)]
+pub(crate) struct Backoff {
+    delay: Duration,
+    initial: Duration,
+    max: Duration,
+}
+
+impl Backoff {
+    pub(crate) fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            delay: initial,
+            initial,
+            max,
+        }
+    }
+
+    /// Delay before the next retry.
+    pub(crate) fn next(&mut self) -> Duration {
+        let delay = self.delay;
+        self.delay = (delay * 2).min(self.max);
+        delay
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.delay = self.initial;
+    }
+}
+
+/// Errors librdkafka does not recover from, e.g. failed authorization. Others, like unreachable brokers, are retried.
+pub(crate) fn is_fatal(err: &KafkaError) -> bool {
+    match err {
+        KafkaError::MessageConsumptionFatal(_) => true,
+        err => matches!(
+            err.rdkafka_error_code(),
+            Some(
+                RDKafkaErrorCode::Fatal
+                    | RDKafkaErrorCode::Authentication
+                    | RDKafkaErrorCode::SaslAuthenticationFailed
+                    | RDKafkaErrorCode::TopicAuthorizationFailed
+                    | RDKafkaErrorCode::GroupAuthorizationFailed
+                    | RDKafkaErrorCode::ClusterAuthorizationFailed
+            )
+        ),
+    }
+}
+
+impl KafkaLog {
+    pub(crate) fn connect(brokers: &str, topic: &str, group: &str) -> Result<Self, EngineError> {
+        let consumer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("group.id", group)
+            .set("enable.auto.commit", "false")
+            .set("enable.auto.offset.store", "false")
+            .create()?;
+
+        Ok(Self {
+            consumer,
+            topic: topic.to_string(),
+            timeout: Duration::from_secs(1),
+            backoff: Backoff::new(Duration::from_millis(100), Duration::from_secs(30)),
+        })
+    }
+}
+
+impl MessageLog for KafkaLog {
+    fn assign(&mut self, offsets: &BTreeMap<i32, i64>) -> Result<(), EngineError> {
+        let metadata = self
+            .consumer
+            .fetch_metadata(Some(&self.topic), Duration::from_secs(10))?;
+        let topic = metadata
+            .topics()
+            .iter()
+            .find(|topic| topic.name() == self.topic && topic.error().is_none())
+            .ok_or_else(|| EngineError::InvalidConfig(format!("topic {} not found", self.topic)))?;
+
+        let mut assignment = TopicPartitionList::new();
+        for partition in topic.partitions() {
+            let offset = offsets
+                .get(&partition.id())
+                .map_or(Offset::Beginning, |offset| Offset::Offset(*offset));
+            assignment.add_partition_offset(&self.topic, partition.id(), offset)?;
+        }
+        self.consumer.assign(&assignment)?;
+
+        Ok(())
+    }
+
+    /// Retriable errors are reported to stderr and end the poll after backoff delay as if there was no record.
+    fn poll(&mut self) -> Result<Option<Record>, EngineError> {
+        match self.consumer.poll(self.timeout) {
+            None => Ok(None),
+            Some(Err(err)) if is_fatal(&err) => Err(err.into()),
+            Some(Err(err)) => {
+                let delay = self.backoff.next();
+                eprintln!("Retrying in {:?} after Kafka error: {}", delay, err);
+                thread::sleep(delay);
+                Ok(None)
+            }
+            Some(Ok(message)) => {
+                self.backoff.reset();
+                Ok(Some(Record {
+                    partition: message.partition(),
+                    offset: message.offset(),
+                    payload: message.payload().unwrap_or_default().to_vec(),
+                }))
+            }
+        }
+    }
+
+    fn commit(&mut self, offsets: &BTreeMap<i32, i64>) -> Result<(), EngineError> {
+        let mut committed = TopicPartitionList::new();
+        for (partition, offset) in offsets {
+            committed.add_partition_offset(&self.topic, *partition, Offset::Offset(*offset))?;
+        }
+        self.consumer.commit(&committed, CommitMode::Sync)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[path = "consumer.test.rs"]
+mod tests;
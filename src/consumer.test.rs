@@ -0,0 +1,266 @@
+use std::{
+    collections::BTreeMap,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    time::Duration,
+};
+
+use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+use rust_decimal_macros::dec;
+
+use crate::{
+    consumer::{is_fatal, log_path, Backoff, Consumer, MessageLog, Record},
+    engine::Engine,
+    testing::temp_path,
+    types::EngineError,
+};
+
+/// In-memory stand-in of Kafka topic.
+#[derive(Default)]
+struct MemoryLog {
+    partitions: BTreeMap<i32, Vec<Vec<u8>>>,
+    positions: BTreeMap<i32, i64>,
+    committed: BTreeMap<i32, i64>,
+    fail_commit: bool,
+}
+
+impl MemoryLog {
+    fn produce(&mut self, partition: i32, payload: &str) {
+        self.partitions
+            .entry(partition)
+            .or_default()
+            .push(payload.as_bytes().to_vec());
+    }
+}
+
+impl MessageLog for &mut MemoryLog {
+    fn assign(&mut self, offsets: &BTreeMap<i32, i64>) -> Result<(), EngineError> {
+        self.positions = self
+            .partitions
+            .keys()
+            .map(|partition| (*partition, offsets.get(partition).copied().unwrap_or(0)))
+            .collect();
+        Ok(())
+    }
+
+    fn poll(&mut self) -> Result<Option<Record>, EngineError> {
+        for (partition, position) in &mut self.positions {
+            if let Some(payload) = self.partitions[partition].get(*position as usize) {
+                let record = Record {
+                    partition: *partition,
+                    offset: *position,
+                    payload: payload.clone(),
+                };
+                *position += 1;
+                return Ok(Some(record));
+            }
+        }
+        Ok(None)
+    }
+
+    fn commit(&mut self, offsets: &BTreeMap<i32, i64>) -> Result<(), EngineError> {
+        if self.fail_commit {
+            return Err(EngineError::InvalidConfig("broker unavailable".to_string()));
+        }
+        self.committed = offsets.clone();
+        Ok(())
+    }
+}
+
+fn checkpoint_path(name: &str) -> PathBuf {
+    let path = temp_path(&format!("consumer-{}.json", name));
+    let _ = std::fs::remove_file(log_path(&path));
+    path
+}
+
+fn remove_checkpoint(path: PathBuf) {
+    let _ = std::fs::remove_file(log_path(&path));
+    let _ = std::fs::remove_file(path);
+}
+
+fn log_lines(path: &Path) -> usize {
+    std::fs::read_to_string(log_path(path))
+        .unwrap_or_default()
+        .lines()
+        .count()
+}
+
+#[test]
+fn test_restart_continues_after_checkpoint() {
+    let path = checkpoint_path("restart");
+    let mut log = MemoryLog::default();
+    log.produce(
+        0,
+        r#"{"type": "deposit", "client": 1, "tx": 1, "amount": "10.0"}"#,
+    );
+    log.produce(
+        1,
+        r#"{"type": "deposit", "client": 2, "tx": 2, "amount": "5.0"}"#,
+    );
+    log.produce(
+        0,
+        r#"{"type": "withdrawal", "client": 1, "tx": 3, "amount": "4.0"}"#,
+    );
+
+    let mut engine = Engine::new();
+    let mut consumer = Consumer::start(&mut log, &mut engine, path.clone(), 2, 100).unwrap();
+    assert_eq!(consumer.poll(&mut engine).unwrap(), 3);
+    drop(consumer);
+    assert_eq!(log.committed, BTreeMap::from([(0, 2), (1, 1)]));
+
+    log.produce(
+        1,
+        r#"{"type": "withdrawal", "client": 2, "tx": 4, "amount": "1.0"}"#,
+    );
+    log.produce(0, r#"{"type": "dispute", "client": 1, "tx": 1}"#);
+
+    // Restarted process starts with empty engine
+    let mut restarted = Engine::new();
+    let mut consumer = Consumer::start(&mut log, &mut restarted, path.clone(), 2, 100).unwrap();
+    assert_eq!(consumer.poll(&mut restarted).unwrap(), 2);
+    drop(consumer);
+    assert_eq!(log.committed, BTreeMap::from([(0, 3), (1, 2)]));
+
+    let client = restarted.client_balance(1).unwrap();
+    assert_eq!(
+        (client.available, client.held, client.total),
+        (dec!(-4.0), dec!(10.0), dec!(6.0))
+    );
+    assert_eq!(restarted.client_balance(2).unwrap().available, dec!(4.0));
+
+    remove_checkpoint(path);
+}
+
+#[test]
+fn test_failed_commit_does_not_repeat_transactions() {
+    let path = checkpoint_path("failed-commit");
+    let mut log = MemoryLog {
+        fail_commit: true,
+        ..Default::default()
+    };
+    log.produce(
+        0,
+        r#"{"type": "deposit", "client": 1, "tx": 1, "amount": "10.0"}"#,
+    );
+
+    let mut engine = Engine::new();
+    let mut consumer = Consumer::start(&mut log, &mut engine, path.clone(), 10, 100).unwrap();
+    assert_eq!(consumer.poll(&mut engine).unwrap(), 1);
+    drop(consumer);
+    assert!(log.committed.is_empty());
+
+    // Committed offsets are behind, checkpoint is not
+    log.fail_commit = false;
+    let mut restarted = Engine::new();
+    let mut consumer = Consumer::start(&mut log, &mut restarted, path.clone(), 10, 100).unwrap();
+    assert_eq!(consumer.poll(&mut restarted).unwrap(), 0);
+    assert_eq!(restarted.client_balance(1).unwrap().available, dec!(10.0));
+
+    remove_checkpoint(path);
+}
+
+#[test]
+fn test_invalid_record_is_skipped() {
+    let path = checkpoint_path("invalid");
+    let mut log = MemoryLog::default();
+    log.produce(0, "deposit, 1, 1, 10.0");
+    log.produce(0, r#"{"type": "deposit", "client": 1, "tx": 2}"#);
+    log.produce(
+        0,
+        r#"{"type": "deposit", "client": 1, "tx": 3, "amount": "1.0"}"#,
+    );
+
+    let mut engine = Engine::new();
+    let mut consumer = Consumer::start(&mut log, &mut engine, path.clone(), 10, 100).unwrap();
+    assert_eq!(consumer.poll(&mut engine).unwrap(), 3);
+    drop(consumer);
+
+    assert_eq!(log.committed, BTreeMap::from([(0, 3)]));
+    assert_eq!(engine.client_balance(1).unwrap().available, dec!(1.0));
+
+    remove_checkpoint(path);
+}
+
+#[test]
+fn test_checkpoints_are_incremental() {
+    let path = checkpoint_path("incremental");
+    let mut log = MemoryLog::default();
+    let (sender, changes) = mpsc::channel();
+    let mut engine = Engine::new();
+
+    // Each run checkpoints one record, every third checkpoint is full one
+    for tx in 1..=5 {
+        log.produce(
+            0,
+            &format!(
+                r#"{{"type": "deposit", "client": 1, "tx": {}, "amount": "1.0"}}"#,
+                tx
+            ),
+        );
+        engine = Engine::new();
+        engine.subscribe(Box::new(sender.clone()));
+        let mut consumer = Consumer::start(&mut log, &mut engine, path.clone(), 1, 3).unwrap();
+        assert_eq!(consumer.poll(&mut engine).unwrap(), 1);
+    }
+    assert_eq!(log_lines(&path), 2);
+    assert_eq!(changes.try_iter().count(), 5);
+
+    // Torn line of interrupted append is dropped
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(log_path(&path))
+        .unwrap();
+    file.write_all(br#"{"number": 6, "offs"#).unwrap();
+
+    let mut restarted = Engine::new();
+    let (sender, replayed) = mpsc::channel();
+    restarted.subscribe(Box::new(sender));
+    let mut consumer = Consumer::start(&mut log, &mut restarted, path.clone(), 1, 3).unwrap();
+    assert_eq!(consumer.poll(&mut restarted).unwrap(), 0);
+    drop(consumer);
+    assert_eq!(restarted.snapshot(), engine.snapshot());
+    // Changes of replayed transactions were published before restart
+    assert_eq!(replayed.try_iter().count(), 0);
+
+    log.produce(
+        0,
+        r#"{"type": "withdrawal", "client": 1, "tx": 6, "amount": "2.0"}"#,
+    );
+    let mut consumer = Consumer::start(&mut log, &mut restarted, path.clone(), 1, 3).unwrap();
+    assert_eq!(consumer.poll(&mut restarted).unwrap(), 1);
+    drop(consumer);
+    assert_eq!(log_lines(&path), 0);
+    assert_eq!(replayed.try_recv().unwrap().sequence, 6);
+    assert_eq!(restarted.client_balance(1).unwrap().available, dec!(3.0));
+
+    remove_checkpoint(path);
+}
+
+#[test]
+fn test_only_fatal_kafka_errors_stop_consumer() {
+    for err in [
+        KafkaError::MessageConsumption(RDKafkaErrorCode::BrokerTransportFailure),
+        KafkaError::MessageConsumption(RDKafkaErrorCode::AllBrokersDown),
+        KafkaError::MessageConsumption(RDKafkaErrorCode::UnknownTopicOrPartition),
+    ] {
+        assert!(!is_fatal(&err), "{}", err);
+    }
+    for err in [
+        KafkaError::MessageConsumptionFatal(RDKafkaErrorCode::Fatal),
+        KafkaError::MessageConsumption(RDKafkaErrorCode::TopicAuthorizationFailed),
+        KafkaError::MessageConsumption(RDKafkaErrorCode::SaslAuthenticationFailed),
+    ] {
+        assert!(is_fatal(&err), "{}", err);
+    }
+}
+
+#[test]
+fn test_backoff_doubles_up_to_max() {
+    let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_millis(300));
+    let delays: Vec<_> = (0..4).map(|_| backoff.next().as_millis()).collect();
+    assert_eq!(delays, [100, 200, 300, 300]);
+
+    backoff.reset();
+    assert_eq!(backoff.next(), Duration::from_millis(100));
+}
@@ -0,0 +1,470 @@
+use std::{collections::HashMap, io::Read, str::FromStr};
+
+use csv::StringRecord;
+use serde::{de::IntoDeserializer, Deserialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    input::{KeyedTransactions, Transactions},
+    types::{Amount, EngineError, Transaction, TransactionType},
+};
+
+/// Column of CSV input identified by header name or zero based index.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum Column {
+    Name(String),
+    Index(usize),
+}
+
+impl Column {
+    /// Numbers are indexes, anything else is header name.
+    pub(crate) fn parse(column: &str) -> Self {
+        match column.parse() {
+            Ok(index) => Column::Index(index),
+            Err(_) => Column::Name(column.to_string()),
+        }
+    }
+
+    /// Index is checked against header, without header it is checked in each record.
+    fn resolve(&self, headers: Option<&StringRecord>) -> Result<usize, EngineError> {
+        match (self, headers) {
+            (Column::Index(index), Some(headers)) if *index >= headers.len() => {
+                Err(EngineError::InvalidDialect(format!(
+                    "column index {} is out of range, header has {} columns",
+                    index,
+                    headers.len()
+                )))
+            }
+            (Column::Index(index), _) => Ok(*index),
+            (Column::Name(name), Some(headers)) => headers
+                .iter()
+                .position(|header| header == name)
+                .ok_or_else(|| EngineError::MissingColumn {
+                    line: line(headers),
+                    column: name.clone(),
+                    value: text(headers),
+                }),
+            (Column::Name(name), None) => Err(EngineError::InvalidDialect(format!(
+                "column {} referenced by name, but input has no header",
+                name
+            ))),
+        }
+    }
+}
+
+/// Columns holding `type`, `client`, `tx` and `amount` transaction fields.
+///
+/// Columns missing in mapping file keep their default names.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct ColumnMapping {
+    #[serde(rename = "type")]
+    pub(crate) tx_type: Column,
+    pub(crate) client: Column,
+    pub(crate) tx: Column,
+    pub(crate) amount: Column,
+}
+
+impl Default for ColumnMapping {
+    fn default() -> Self {
+        Self {
+            tx_type: Column::Name(COLUMNS[0].to_string()),
+            client: Column::Name(COLUMNS[1].to_string()),
+            tx: Column::Name(COLUMNS[2].to_string()),
+            amount: Column::Name(COLUMNS[3].to_string()),
+        }
+    }
+}
+
+impl ColumnMapping {
+    /// Parse comma separated list of 4 columns in order `type,client,tx,amount`, e.g. `kind,account,id,value` or `0,2,1,3`.
+    pub(crate) fn parse(columns: &str) -> Result<Self, EngineError> {
+        let columns: Vec<_> = columns.split(',').map(str::trim).collect();
+        let [tx_type, client, tx, amount] = columns[..] else {
+            return Err(EngineError::InvalidDialect(format!(
+                "expected 4 columns (type,client,tx,amount), got {}",
+                columns.len()
+            )));
+        };
+
+        Ok(Self {
+            tx_type: Column::parse(tx_type),
+            client: Column::parse(client),
+            tx: Column::parse(tx),
+            amount: Column::parse(amount),
+        })
+    }
+
+    fn columns(&self) -> [&Column; 4] {
+        [&self.tx_type, &self.client, &self.tx, &self.amount]
+    }
+
+    /// Indexes of mapped columns in order `type,client,tx,amount`.
+    fn resolve(&self, headers: Option<&StringRecord>) -> Result<[usize; 4], EngineError> {
+        let mut indexes = [0; 4];
+        for (index, column) in indexes.iter_mut().zip(self.columns()) {
+            *index = column.resolve(headers)?;
+        }
+        Ok(indexes)
+    }
+}
+
+/// CSV input settings. Default matches the original input: comma delimited with header and double quotes.
+///
+/// Fields are always trimmed and UTF-8 BOM is stripped.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CsvDialect {
+    pub(crate) delimiter: u8,
+    pub(crate) quote: u8,
+    pub(crate) has_headers: bool,
+    /// Lines starting with this character are skipped
+    pub(crate) comment: Option<u8>,
+    /// Columns matched by header names `type`, `client`, `tx` and `amount` when not set,
+    /// or by position in this order when input has no header
+    pub(crate) columns: Option<ColumnMapping>,
+    /// Partner specific type tokens translated to `deposit`, `withdrawal`, ... before deserialization
+    pub(crate) type_names: HashMap<String, String>,
+}
+
+impl Default for CsvDialect {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            quote: b'"',
+            has_headers: true,
+            comment: None,
+            columns: None,
+            type_names: HashMap::new(),
+        }
+    }
+}
+
+impl CsvDialect {
+    /// Parse single character setting, `\t` or `tab` is tab.
+    pub(crate) fn parse_char(name: &str, value: &str) -> Result<u8, EngineError> {
+        match value {
+            "\\t" | "tab" => Ok(b'\t'),
+            _ if value.len() == 1 && value.is_ascii() => Ok(value.as_bytes()[0]),
+            _ => Err(EngineError::InvalidDialect(format!(
+                "{} must be a single ASCII character, got {:?}",
+                name, value
+            ))),
+        }
+    }
+
+    /// Check settings before any input is read.
+    pub(crate) fn validate(&self) -> Result<(), EngineError> {
+        let mut special = vec![("delimiter", self.delimiter), ("quote", self.quote)];
+        special.extend(self.comment.map(|comment| ("comment", comment)));
+
+        for (i, (name, char)) in special.iter().enumerate() {
+            if matches!(char, b'\n' | b'\r') || char.is_ascii_alphanumeric() {
+                return Err(EngineError::InvalidDialect(format!(
+                    "{} must not be a newline, letter or digit",
+                    name
+                )));
+            }
+            if let Some((other, _)) = special[..i].iter().find(|(_, other)| other == char) {
+                return Err(EngineError::InvalidDialect(format!(
+                    "{} must differ from {}",
+                    name, other
+                )));
+            }
+        }
+
+        if let Some(columns) = &self.columns {
+            if !self.has_headers
+                && columns
+                    .columns()
+                    .iter()
+                    .any(|column| matches!(column, Column::Name(_)))
+            {
+                return Err(EngineError::InvalidDialect(
+                    "columns must be referenced by index when input has no header".to_string(),
+                ));
+            }
+
+            let columns = columns.columns();
+            if columns
+                .iter()
+                .enumerate()
+                .any(|(i, column)| columns[..i].contains(column))
+            {
+                return Err(EngineError::InvalidDialect(
+                    "columns must not repeat".to_string(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) fn read_csv(
+    reader: Box<dyn Read>,
+    dialect: &CsvDialect,
+) -> Result<Transactions, EngineError> {
+    Ok(Box::new(
+        read_records(reader, dialect, None)?
+            .map(|result| result.map(|(_, transaction)| transaction)),
+    ))
+}
+
+/// Transactions with value of `key` column, which is allowed besides the standard columns.
+pub(crate) fn read_csv_keyed(
+    reader: Box<dyn Read>,
+    dialect: &CsvDialect,
+    key: &Column,
+) -> Result<KeyedTransactions, EngineError> {
+    read_records(reader, dialect, Some(key))
+}
+
+/// Key is empty when `key` column is not set.
+fn read_records(
+    reader: Box<dyn Read>,
+    dialect: &CsvDialect,
+    key: Option<&Column>,
+) -> Result<KeyedTransactions, EngineError> {
+    dialect.validate()?;
+
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .delimiter(dialect.delimiter)
+        .quote(dialect.quote)
+        .has_headers(dialect.has_headers)
+        .comment(dialect.comment)
+        .from_reader(reader);
+
+    let headers = match dialect.has_headers {
+        true => Some(reader.headers()?.clone()),
+        false => None,
+    };
+    let columns = dialect.columns.clone().unwrap_or_default();
+    let indexes = match (&dialect.columns, &headers) {
+        (None, None) => [0, 1, 2, 3],
+        _ => columns.resolve(headers.as_ref())?,
+    };
+    let names = match &headers {
+        Some(headers) => indexes.map(|index| header(headers, index)),
+        None => COLUMNS.map(str::to_string),
+    };
+
+    let key_index = key.map(|key| key.resolve(headers.as_ref())).transpose()?;
+    if key_index.is_some_and(|key_index| indexes.contains(&key_index)) {
+        return Err(EngineError::InvalidDialect(
+            "key column must differ from transaction columns".to_string(),
+        ));
+    }
+    let key_name = key_index.map(|index| match &headers {
+        Some(headers) => header(headers, index),
+        None => index.to_string(),
+    });
+
+    // Without explicit mapping input must have exactly the standard columns and key column
+    let strict = dialect.columns.is_none();
+    if let (true, Some(headers)) = (strict, &headers) {
+        if let Some(column) = headers
+            .iter()
+            .find(|header| !COLUMNS.contains(header) && Some(*header) != key_name.as_deref())
+        {
+            return Err(EngineError::UnknownColumn {
+                line: line(headers),
+                column: column.to_string(),
+                value: column.to_string(),
+            });
+        }
+    }
+    let max_len = COLUMNS.len() + usize::from(key_index.is_some());
+
+    let type_names = dialect.type_names.clone();
+
+    Ok(Box::new(reader.into_records().map(move |result| {
+        let record = result?;
+        if strict && record.len() > max_len {
+            return Err(EngineError::UnknownColumn {
+                line: line(&record),
+                column: max_len.to_string(),
+                value: record[max_len].to_string(),
+            });
+        }
+
+        let key = match (key_index, &key_name) {
+            (Some(index), Some(name)) => record
+                .get(index)
+                .ok_or_else(|| EngineError::MissingColumn {
+                    line: line(&record),
+                    column: name.clone(),
+                    value: text(&record),
+                })?
+                .to_string(),
+            _ => String::new(),
+        };
+        Ok((key, parse_record(&record, &indexes, &names, &type_names)?))
+    })))
+}
+
+/// Parse single line in standard column order `type, client, tx, amount`, used for line oriented input.
+pub(crate) fn parse_line(line: &str, number: u64) -> Result<Transaction, EngineError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .has_headers(false)
+        .from_reader(line.as_bytes());
+    let mut record = StringRecord::new();
+    reader.read_record(&mut record)?;
+
+    let mut position = csv::Position::new();
+    position.set_line(number);
+    record.set_position(Some(position));
+
+    if record.len() > COLUMNS.len() {
+        return Err(EngineError::UnknownColumn {
+            line: number,
+            column: COLUMNS.len().to_string(),
+            value: record[COLUMNS.len()].to_string(),
+        });
+    }
+    parse_record(
+        &record,
+        &[0, 1, 2, 3],
+        &COLUMNS.map(str::to_string),
+        &HashMap::new(),
+    )
+}
+
+/// Parse JSON object keyed by standard column names, validated like CSV record in line `number`.
+///
+/// Strings and numbers are taken as field text, null is an empty field.
+pub(crate) fn parse_object(
+    object: &Map<String, Value>,
+    number: u64,
+) -> Result<Transaction, EngineError> {
+    let field = |value: &Value| match value {
+        Value::String(text) => text.trim().to_string(),
+        Value::Null => String::new(),
+        value => value.to_string(),
+    };
+
+    if let Some((key, value)) = object
+        .iter()
+        .find(|(key, _)| !COLUMNS.contains(&key.as_str()))
+    {
+        return Err(EngineError::UnknownColumn {
+            line: number,
+            column: key.clone(),
+            value: field(value),
+        });
+    }
+    // Amount is optional like the trailing CSV column
+    if let Some(column) = COLUMNS[..3]
+        .iter()
+        .find(|column| !object.contains_key(**column))
+    {
+        return Err(EngineError::MissingColumn {
+            line: number,
+            column: column.to_string(),
+            value: Value::Object(object.clone()).to_string(),
+        });
+    }
+
+    let mut record: StringRecord = COLUMNS
+        .iter()
+        .map(|column| object.get(*column).map(field).unwrap_or_default())
+        .collect();
+    let mut position = csv::Position::new();
+    position.set_line(number);
+    record.set_position(Some(position));
+
+    parse_record(
+        &record,
+        &[0, 1, 2, 3],
+        &COLUMNS.map(str::to_string),
+        &HashMap::new(),
+    )
+}
+
+/// Default column names in order of [`ColumnMapping`].
+const COLUMNS: [&str; 4] = ["type", "client", "tx", "amount"];
+
+/// Name of header at `index`, index itself when out of range.
+fn header(headers: &StringRecord, index: usize) -> String {
+    headers
+        .get(index)
+        .map_or_else(|| index.to_string(), str::to_string)
+}
+
+/// Fields of record joined by comma, as shown in errors.
+fn text(record: &StringRecord) -> String {
+    record.iter().collect::<Vec<_>>().join(",")
+}
+
+fn line(record: &StringRecord) -> u64 {
+    record.position().map_or(0, |position| position.line())
+}
+
+/// Parse fields of record at `indexes` named `names` into transaction.
+fn parse_record(
+    record: &StringRecord,
+    indexes: &[usize; 4],
+    names: &[String; 4],
+    type_names: &HashMap<String, String>,
+) -> Result<Transaction, EngineError> {
+    let line = line(record);
+    let field = |i: usize| {
+        record
+            .get(indexes[i])
+            .ok_or_else(|| EngineError::MissingColumn {
+                line,
+                column: names[i].clone(),
+                value: text(record),
+            })
+    };
+
+    let tx_type = field(0)?;
+    let translated = type_names.get(tx_type).map_or(tx_type, String::as_str);
+    let tx_type = TransactionType::deserialize(translated.into_deserializer()).map_err(
+        |_: serde::de::value::Error| EngineError::BadTypeToken {
+            line,
+            column: names[0].clone(),
+            value: tx_type.to_string(),
+        },
+    )?;
+
+    let client = field(1)?;
+    let client = client.parse().map_err(|_| EngineError::NonNumericClient {
+        line,
+        column: names[1].clone(),
+        value: client.to_string(),
+    })?;
+
+    let tx = field(2)?;
+    let tx = tx.parse().map_err(|_| EngineError::NonIntegerTx {
+        line,
+        column: names[2].clone(),
+        value: tx.to_string(),
+    })?;
+
+    let amount = match record.get(indexes[3]) {
+        None | Some("") => None,
+        Some(amount) => Some(
+            Amount::from_str(amount).map_err(|_| EngineError::BadDecimal {
+                line,
+                column: names[3].clone(),
+                value: amount.to_string(),
+            })?,
+        ),
+    };
+
+    Ok(Transaction {
+        tx_type,
+        client,
+        tx,
+        amount,
+    })
+}
+
+#[cfg(test)]
+#[path = "dialect.test.rs"]
+mod tests;
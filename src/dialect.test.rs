@@ -0,0 +1,225 @@
+use rust_decimal_macros::dec;
+
+use crate::{
+    dialect::{parse_line, read_csv, Column, ColumnMapping, CsvDialect},
+    types::{EngineError, Transaction, TransactionType},
+};
+
+fn expected_transactions() -> Vec<Transaction> {
+    vec![
+        Transaction {
+            tx_type: TransactionType::Deposit,
+            client: 1,
+            tx: 1,
+            amount: Some(dec!(1.5)),
+        },
+        Transaction {
+            tx_type: TransactionType::Dispute,
+            client: 1,
+            tx: 1,
+            amount: None,
+        },
+    ]
+}
+
+fn read(input: &'static str, dialect: &CsvDialect) -> Result<Vec<Transaction>, EngineError> {
+    read_csv(Box::new(input.as_bytes()), dialect)?.collect()
+}
+
+#[test]
+fn test_delimiter() {
+    let dialect = CsvDialect {
+        delimiter: b';',
+        ..Default::default()
+    };
+    let input = "type; client; tx; amount\ndeposit; 1; 1; 1.5\ndispute; 1; 1;\n";
+    assert_eq!(read(input, &dialect).unwrap(), expected_transactions());
+}
+
+#[test]
+fn test_no_header() {
+    let dialect = CsvDialect {
+        has_headers: false,
+        ..Default::default()
+    };
+    let input = "deposit, 1, 1, 1.5\ndispute, 1, 1\n";
+    assert_eq!(read(input, &dialect).unwrap(), expected_transactions());
+
+    let dialect = CsvDialect {
+        has_headers: false,
+        columns: Some(ColumnMapping::parse("1,0,2,3").unwrap()),
+        ..Default::default()
+    };
+    let input = "1, deposit, 1, 1.5\n1, dispute, 1\n";
+    assert_eq!(read(input, &dialect).unwrap(), expected_transactions());
+}
+
+#[test]
+fn test_columns_by_name() {
+    let dialect = CsvDialect {
+        columns: Some(ColumnMapping::parse("kind, account, id, value").unwrap()),
+        ..Default::default()
+    };
+    let input =
+        "id, value, note, account, kind\n1, 1.5, first, 1, deposit\n1, , second, 1, dispute\n";
+    assert_eq!(read(input, &dialect).unwrap(), expected_transactions());
+
+    let input = "id, value, account, type\n1, 1.5, 1, deposit\n";
+    assert!(matches!(
+        read(input, &dialect),
+        Err(EngineError::MissingColumn { line: 1, column, value }) if column == "kind" && value == "id,value,account,type"
+    ));
+}
+
+#[test]
+fn test_column_index_out_of_header() {
+    let dialect = CsvDialect {
+        columns: Some(ColumnMapping::parse("0,1,2,7").unwrap()),
+        ..Default::default()
+    };
+    let input = "type, client, tx, amount\ndeposit, 1, 1, 1.0\n";
+    assert_eq!(
+        read(input, &dialect).unwrap_err().to_string(),
+        "Invalid CSV dialect: column index 7 is out of range, header has 4 columns"
+    );
+}
+
+#[test]
+fn test_comment_and_quote() {
+    let dialect = CsvDialect {
+        quote: b'\'',
+        comment: Some(b'#'),
+        ..Default::default()
+    };
+    let input = "# exported by partner\ntype,client,tx,amount\n'deposit',1,1,'1.5'\n# disputed\ndispute,1,1\n";
+    assert_eq!(read(input, &dialect).unwrap(), expected_transactions());
+}
+
+#[test]
+fn test_byte_order_mark() {
+    let input = "\u{feff}type, client, tx, amount\ndeposit, 1, 1, 1.5\ndispute, 1, 1\n";
+    assert_eq!(
+        read(input, &CsvDialect::default()).unwrap(),
+        expected_transactions()
+    );
+}
+
+#[test]
+fn test_parse_settings() {
+    assert_eq!(CsvDialect::parse_char("delimiter", "\\t").unwrap(), b'\t');
+    assert_eq!(CsvDialect::parse_char("delimiter", "|").unwrap(), b'|');
+    assert!(CsvDialect::parse_char("delimiter", ";;").is_err());
+
+    assert_eq!(
+        ColumnMapping::parse("kind,2,id,3").unwrap(),
+        ColumnMapping {
+            tx_type: Column::Name("kind".to_string()),
+            client: Column::Index(2),
+            tx: Column::Name("id".to_string()),
+            amount: Column::Index(3),
+        }
+    );
+    assert!(ColumnMapping::parse("type,client,tx").is_err());
+}
+
+#[test]
+fn test_validate() {
+    assert!(CsvDialect::default().validate().is_ok());
+
+    let invalid = [
+        CsvDialect {
+            delimiter: b'"',
+            ..Default::default()
+        },
+        CsvDialect {
+            comment: Some(b','),
+            ..Default::default()
+        },
+        CsvDialect {
+            delimiter: b'a',
+            ..Default::default()
+        },
+        CsvDialect {
+            has_headers: false,
+            columns: Some(ColumnMapping::parse("0,client,2,3").unwrap()),
+            ..Default::default()
+        },
+        CsvDialect {
+            columns: Some(ColumnMapping::parse("0,1,1,3").unwrap()),
+            ..Default::default()
+        },
+    ];
+    for dialect in invalid {
+        assert!(
+            matches!(dialect.validate(), Err(EngineError::InvalidDialect(_))),
+            "{:?}",
+            dialect
+        );
+    }
+}
+
+fn read_error(input: &'static str) -> String {
+    read(input, &CsvDialect::default()).unwrap_err().to_string()
+}
+
+#[test]
+fn test_invalid_input() {
+    assert!(matches!(
+        read("type, client, tx, amount, extra\ndeposit, 1, 1, 1.0\n", &CsvDialect::default()),
+        Err(EngineError::UnknownColumn { line: 1, column, value }) if column == "extra" && value == "extra"
+    ));
+    assert!(matches!(
+        read("type, client, tx, amount\ndeposit, 1, 1, 1.0, 2\n", &CsvDialect::default()),
+        Err(EngineError::UnknownColumn { line: 2, column, value }) if column == "4" && value == "2"
+    ));
+    assert!(matches!(
+        read("type, client, amount\ndeposit, 1, 1.0\n", &CsvDialect::default()),
+        Err(EngineError::MissingColumn { line: 1, column, .. }) if column == "tx"
+    ));
+    assert!(matches!(
+        read("type, client, tx, amount\ndeposit, 1, 1, 1.0\ndeposit, 1\n", &CsvDialect::default()),
+        Err(EngineError::MissingColumn { line: 3, column, value }) if column == "tx" && value == "deposit,1"
+    ));
+
+    assert_eq!(
+        read_error("type, client, tx, amount\ndeposittt, 1, 1, 1.0\n"),
+        "Unknown transaction type \"deposittt\" in line 2, column type"
+    );
+    assert_eq!(
+        read_error("type, client, tx, amount\ndeposit, one, 1, 1.0\n"),
+        "Client id must be an integer from 0 to 65535, got \"one\" in line 2, column client"
+    );
+    assert_eq!(
+        read_error("type, client, tx, amount\ndeposit, 1, 1.0, 1.0\n"),
+        "Transaction id must be an integer from 0 to 4294967295, got \"1.0\" in line 2, column tx"
+    );
+    assert_eq!(
+        read_error("type, client, tx, amount\ndeposit, 1, 1, 1,0\n"),
+        "Unknown column 4 with value \"0\" in line 2"
+    );
+    assert_eq!(
+        read_error("type, client, tx, amount\ndeposit, 1, 1, 1.0.0\n"),
+        "Invalid decimal amount \"1.0.0\" in line 2, column amount"
+    );
+}
+
+#[test]
+fn test_invalid_input_with_mapping() {
+    let dialect = CsvDialect {
+        columns: Some(ColumnMapping::parse("kind, account, id, value").unwrap()),
+        ..Default::default()
+    };
+    let input = "id, value, note, account, kind\n1, 1.5, first, 1, credit\n";
+    assert!(matches!(
+        read(input, &dialect),
+        Err(EngineError::BadTypeToken { line: 2, column, value }) if column == "kind" && value == "credit"
+    ));
+}
+
+#[test]
+fn test_parse_line_with_extra_field() {
+    assert!(matches!(
+        parse_line("deposit, 1, 1, 1,5", 7),
+        Err(EngineError::UnknownColumn { line: 7, column, value }) if column == "4" && value == "5"
+    ));
+}
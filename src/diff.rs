@@ -0,0 +1,96 @@
+use std::{collections::BTreeMap, fmt};
+
+use crate::{output::ClientBalance, types::ClientId};
+
+/// Difference of client accounts between two reports.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Difference {
+    /// Field `available`, `held`, `total` or `locked` with its left and right value
+    Field {
+        client: ClientId,
+        field: &'static str,
+        left: String,
+        right: String,
+    },
+    /// Client exists only on side `left` or `right`
+    OnlyIn {
+        client: ClientId,
+        side: &'static str,
+    },
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Difference::Field {
+                client,
+                field,
+                left,
+                right,
+            } => write!(f, "client {}: {} {} != {}", client, field, left, right),
+            Difference::OnlyIn { client, side } => write!(f, "client {}: only in {}", client, side),
+        }
+    }
+}
+
+/// Differences of balances ordered by client id, other columns of report are not compared.
+pub(crate) fn diff(left: &[ClientBalance], right: &[ClientBalance]) -> Vec<Difference> {
+    let mut clients: BTreeMap<ClientId, (Option<&ClientBalance>, Option<&ClientBalance>)> =
+        BTreeMap::new();
+    for balance in left {
+        clients.entry(balance.client).or_default().0 = Some(balance);
+    }
+    for balance in right {
+        clients.entry(balance.client).or_default().1 = Some(balance);
+    }
+
+    let mut differences = Vec::new();
+    for (client, sides) in clients {
+        let (left, right) = match sides {
+            (Some(left), Some(right)) => (left, right),
+            (Some(_), None) => {
+                differences.push(Difference::OnlyIn {
+                    client,
+                    side: "left",
+                });
+                continue;
+            }
+            _ => {
+                differences.push(Difference::OnlyIn {
+                    client,
+                    side: "right",
+                });
+                continue;
+            }
+        };
+
+        let amounts = [
+            ("available", left.available, right.available),
+            ("held", left.held, right.held),
+            ("total", left.total, right.total),
+        ];
+        for (field, left, right) in amounts {
+            if left != right {
+                differences.push(Difference::Field {
+                    client,
+                    field,
+                    left: left.to_string(),
+                    right: right.to_string(),
+                });
+            }
+        }
+        if left.locked != right.locked {
+            differences.push(Difference::Field {
+                client,
+                field: "locked",
+                left: left.locked.to_string(),
+                right: right.locked.to_string(),
+            });
+        }
+    }
+    differences
+}
+
+#[cfg(test)]
+#[path = "diff.test.rs"]
+mod tests;
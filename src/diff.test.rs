@@ -0,0 +1,56 @@
+use rust_decimal_macros::dec;
+
+use crate::{
+    diff::{diff, Difference},
+    output::ClientBalance,
+    types::Amount,
+};
+
+fn balance(client: u16, available: Amount, held: Amount, locked: bool) -> ClientBalance {
+    ClientBalance {
+        client,
+        available,
+        held,
+        total: available + held,
+        locked,
+        open_disputes: 0,
+        limit_breaches: None,
+        fees: None,
+    }
+}
+
+#[test]
+fn test_diff_of_equal_balances_is_empty() {
+    let left = [balance(1, dec!(1.5), dec!(0), false)];
+    let right = [balance(1, dec!(1.50), dec!(0.0), false)];
+
+    assert_eq!(diff(&left, &right), vec![]);
+}
+
+#[test]
+fn test_diff_reports_fields_and_missing_clients() {
+    let left = [
+        balance(1, dec!(1.5), dec!(0), false),
+        balance(2, dec!(1), dec!(0), false),
+    ];
+    let right = [
+        balance(1, dec!(2.0), dec!(0), true),
+        balance(3, dec!(1), dec!(0), false),
+    ];
+
+    let differences: Vec<_> = diff(&left, &right)
+        .iter()
+        .map(Difference::to_string)
+        .collect();
+
+    assert_eq!(
+        differences,
+        vec![
+            "client 1: available 1.5 != 2.0",
+            "client 1: total 1.5 != 2.0",
+            "client 1: locked false != true",
+            "client 2: only in left",
+            "client 3: only in right",
+        ]
+    );
+}
@@ -1,63 +1,883 @@
 use std::collections::HashMap;
 
-use crate::types::{
-    Client, ClientId, DisputeState, EngineError, StoredTransaction, Transaction, TransactionType,
+use rust_decimal::Decimal;
+
+use crate::{
+    config::EngineConfig,
+    feed::{AccountChanged, ChangeFeed, ChangeSink},
+    input::{self, InputOptions},
+    invariants::{self, InvariantCheck, InvariantViolation},
+    journal::{Journal, UndoStep},
+    ledger::{Account, Ledger},
+    limits::WithdrawalHistory,
+    output::{self, ClientBalance, OutputFormat},
+    risk::{self, RiskDecision, RiskRule},
+    snapshot::Snapshot,
+    store::{Changes, ClientState, Store, Update},
+    types::{
+        Amount, Client, ClientId, DisputeState, EngineError, RejectReason, StoredTransaction,
+        Transaction, TransactionId, TransactionOutcome, TransactionType,
+    },
 };
 
+/// Transaction applied despite being flagged by risk rule.
+#[derive(Debug, PartialEq)]
+pub(crate) struct FlaggedTransaction {
+    pub(crate) client: ClientId,
+    pub(crate) tx: TransactionId,
+    pub(crate) rule: &'static str,
+}
+
 pub struct Engine {
     clients: HashMap<ClientId, Client>,
+    config: EngineConfig,
+    /// Number of processed transactions, used as clock for withdrawal limit windows
+    sequence: u64,
+    withdrawal_history: HashMap<ClientId, WithdrawalHistory>,
+    limit_breaches: HashMap<ClientId, usize>,
+    risk_rules: Vec<Box<dyn RiskRule>>,
+    flagged: Vec<FlaggedTransaction>,
+    /// Clients locked by risk rule with given name
+    locked_by_rule: HashMap<ClientId, &'static str>,
+    invariant_check: InvariantCheck,
+    /// Source of truth for client `available` and `held` amounts
+    ledger: Ledger,
+    feed: ChangeFeed,
+    store: Option<Box<dyn Store>>,
+    /// Undo steps of recent transactions, empty unless rollback is enabled
+    journal: Journal,
+    /// Admin transactions are refused unless input is trusted, see [`Engine::allow_admin_transactions`]
+    admin: bool,
 }
 
 impl Engine {
     pub fn new() -> Self {
+        Self::with_config(EngineConfig::default())
+    }
+
+    /// Engine with built-in risk rules enabled in config.
+    pub(crate) fn with_config(config: EngineConfig) -> Self {
+        let risk_rules = risk::builtin_rules(&config.risk_rules);
+        Self::with_risk_rules(config, risk_rules)
+    }
+
+    /// Engine evaluating given risk rules in order instead of the built-in ones.
+    pub(crate) fn with_risk_rules(
+        config: EngineConfig,
+        risk_rules: Vec<Box<dyn RiskRule>>,
+    ) -> Self {
         Self {
             clients: HashMap::new(),
+            config,
+            sequence: 0,
+            withdrawal_history: HashMap::new(),
+            limit_breaches: HashMap::new(),
+            risk_rules,
+            flagged: Vec::new(),
+            locked_by_rule: HashMap::new(),
+            invariant_check: InvariantCheck::Off,
+            ledger: Ledger::default(),
+            feed: ChangeFeed::default(),
+            store: None,
+            journal: Journal::default(),
+            admin: false,
         }
     }
 
-    pub fn read_and_process_input(&mut self, filename: &String) -> Result<(), EngineError> {
-        let mut reader = csv::ReaderBuilder::new()
-            .trim(csv::Trim::All)
-            .flexible(true)
-            .from_path(filename)?;
+    /// Process inputs as one stream, errors are prefixed with file name when there are more inputs.
+    pub(crate) fn read_and_process_input(
+        &mut self,
+        filenames: &[String],
+        options: &InputOptions,
+    ) -> Result<(), EngineError> {
+        for (index, result) in input::read_inputs(filenames, options) {
+            let in_file = |err| match filenames.len() {
+                1 => err,
+                _ => EngineError::InFile {
+                    filename: filenames[index].clone(),
+                    source: Box::new(err),
+                },
+            };
+            let transaction = result.map_err(in_file)?;
+            self.process_transaction(transaction).map_err(in_file)?;
+        }
 
-        for result in reader.deserialize() {
-            self.process_transaction(result?)?;
+        self.check_invariants_at_end()
+    }
+
+    /// Process each input in its own batch, input with invalid record or failed transaction is rolled back as a whole.
+    ///
+    /// Processing continues with the next input, errors of rolled back inputs are returned.
+    pub(crate) fn read_and_process_atomically(
+        &mut self,
+        filenames: &[String],
+        options: &InputOptions,
+    ) -> Result<Vec<EngineError>, EngineError> {
+        let mut rolled_back = Vec::new();
+        for filename in filenames {
+            self.begin()?;
+            let result = input::read_inputs(std::slice::from_ref(filename), options)
+                .try_for_each(|(_, result)| self.process_transaction(result?).map(|_| ()));
+
+            match result {
+                Ok(()) => self.commit()?,
+                Err(err) => {
+                    self.rollback()?;
+                    rolled_back.push(EngineError::InFile {
+                        filename: filename.clone(),
+                        source: Box::new(err),
+                    });
+                }
+            }
         }
 
+        self.check_invariants_at_end()?;
+        Ok(rolled_back)
+    }
+
+    pub(crate) fn check_invariants_at_end(&self) -> Result<(), EngineError> {
+        if self.invariant_check == InvariantCheck::End {
+            let violations = self.check_invariants();
+            if !violations.is_empty() {
+                return Err(EngineError::InvariantViolated(describe(&violations)));
+            }
+        }
         Ok(())
     }
 
-    pub fn print_clients(&self) {
-        println!("client, available, held, total, locked");
+    pub(crate) fn print_clients(&self, format: OutputFormat) -> Result<(), EngineError> {
+        output::print_balances(&self.client_balances(), format)
+    }
+
+    /// Final report ordered by client id. Column `limit_breaches` is present only when withdrawal limits are configured,
+    /// column `fees` only when fees are configured.
+    pub(crate) fn client_balances(&self) -> Vec<ClientBalance> {
+        let client_fees = self.config.has_fees().then(|| self.client_fees());
 
-        for (client_id, client) in &self.clients {
-            println!(
-                "{}, {}, {}, {}, {}",
-                client_id,
-                client.available,
-                client.held,
-                client.available + client.held,
-                client.locked
+        let mut balances: Vec<_> = self
+            .clients
+            .iter()
+            .map(|(client_id, client)| self.balance(*client_id, client, client_fees))
+            .collect();
+        balances.sort_by_key(|balance| balance.client);
+        balances
+    }
+
+    pub(crate) fn client_balance(&self, client_id: ClientId) -> Option<ClientBalance> {
+        let client = self.clients.get(&client_id)?;
+        let client_fees = self.config.has_fees().then(|| self.client_fees());
+        Some(self.balance(client_id, client, client_fees))
+    }
+
+    /// Client state including stored transactions and their dispute states.
+    pub(crate) fn client(&self, client_id: ClientId) -> Option<&Client> {
+        self.clients.get(&client_id)
+    }
+
+    fn balance(
+        &self,
+        client_id: ClientId,
+        client: &Client,
+        client_fees: Option<&HashMap<ClientId, Amount>>,
+    ) -> ClientBalance {
+        ClientBalance {
+            client: client_id,
+            available: client.available,
+            held: client.held,
+            total: client.available + client.held,
+            locked: client.locked,
+            open_disputes: client
+                .transactions
+                .values()
+                .filter(|stored| stored.dispute_state == DisputeState::Open)
+                .count(),
+            limit_breaches: self
+                .config
+                .has_withdrawal_limits()
+                .then(|| self.limit_breaches.get(&client_id).copied().unwrap_or(0)),
+            fees: client_fees.map(|client_fees| {
+                client_fees
+                    .get(&client_id)
+                    .copied()
+                    .unwrap_or(Decimal::ZERO)
+            }),
+        }
+    }
+
+    /// Report of transactions flagged by risk rules and house account balance,
+    /// printed to stderr to keep stdout for clients report.
+    pub fn print_summary(&self) {
+        for flagged in &self.flagged {
+            eprintln!(
+                "Flagged transaction {} of client {} by risk rule {}",
+                flagged.tx, flagged.client, flagged.rule
             );
         }
+
+        if self.config.has_fees() {
+            eprintln!("House account: {}", self.house_balance());
+        }
     }
 
-    /// Process all types of transactions.
+    /// Resume from state saved in `store` and save there each processed transaction.
     ///
-    /// Note: Not splitting processing of particular transaction types into separate functions as actual processing is quite simple.
-    fn process_transaction(&mut self, transaction: Transaction) -> Result<(), EngineError> {
+    /// State of all clients is saved right away, which also moves it out of `engine_state` of older databases.
+    pub(crate) fn open_store(&mut self, mut store: Box<dyn Store>) -> Result<(), EngineError> {
+        let restored = match store.load()? {
+            Some(snapshot) => {
+                self.restore(snapshot)?;
+                true
+            }
+            None => false,
+        };
+        self.store = Some(store);
+
+        if restored {
+            let mut client_ids: Vec<_> = self
+                .clients
+                .keys()
+                .chain(self.config.clients.keys())
+                .chain(self.withdrawal_history.keys())
+                .chain(self.limit_breaches.keys())
+                .chain(self.locked_by_rule.keys())
+                .copied()
+                .collect();
+            client_ids.sort();
+            client_ids.dedup();
+            let touched: Vec<_> = client_ids
+                .into_iter()
+                .map(|client_id| (client_id, None))
+                .collect();
+            self.save(&touched)?;
+        }
+        Ok(())
+    }
+
+    /// Send [`AccountChanged`] to `sink` after each transaction changing client account.
+    pub(crate) fn subscribe(&mut self, sink: Box<dyn ChangeSink>) {
+        self.feed.subscribe(sink);
+    }
+
+    pub(crate) fn set_invariant_check(&mut self, invariant_check: InvariantCheck) {
+        self.invariant_check = invariant_check;
+    }
+
+    /// Validate all clients against their stored transactions, ordered by client id.
+    pub(crate) fn check_invariants(&self) -> Vec<InvariantViolation> {
+        let mut client_ids: Vec<_> = self.clients.keys().collect();
+        client_ids.sort();
+
+        client_ids
+            .into_iter()
+            .flat_map(|client_id| self.check_client_invariants(*client_id))
+            .collect()
+    }
+
+    fn check_client_invariants(&self, client_id: ClientId) -> Vec<InvariantViolation> {
+        match self.clients.get(&client_id) {
+            Some(client) => invariants::check_client(
+                client_id,
+                client,
+                self.locked_by_rule.contains_key(&client_id),
+            ),
+            None => Vec::new(),
+        }
+    }
+
+    /// Trial balance of all ledger accounts, the total is always zero.
+    pub fn print_trial_balance(&self) {
+        println!("account, balance");
+
+        for (account, balance) in self.ledger.trial_balance() {
+            println!("{}, {}", account, balance);
+        }
+    }
+
+    /// Check that ledger balances and client amounts are derived from it.
+    pub fn verify_ledger(&self) -> Result<(), EngineError> {
+        self.ledger.verify()?;
+
+        for (client_id, client) in &self.clients {
+            if client.available != self.ledger.balance(Account::Available(*client_id))
+                || client.held != self.ledger.balance(Account::Held(*client_id))
+            {
+                return Err(EngineError::LedgerImbalance(format!(
+                    "client {} amounts do not match ledger",
+                    client_id
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Copy of complete engine state, engine restored from it continues exactly like this one.
+    pub(crate) fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            sequence: self.sequence,
+            changes: self.feed.sequence,
+            clients: self.clients.clone(),
+            credit_limits: self
+                .config
+                .clients
+                .iter()
+                .map(|(client_id, client_config)| (*client_id, client_config.credit_limit))
+                .collect(),
+            postings: self.ledger.postings().to_vec(),
+            withdrawal_history: self.withdrawal_history.clone(),
+            limit_breaches: self.limit_breaches.clone(),
+            flagged: self
+                .flagged
+                .iter()
+                .map(|flagged| (flagged.client, flagged.tx, flagged.rule.to_string()))
+                .collect(),
+            locked_by_rule: self
+                .locked_by_rule
+                .iter()
+                .map(|(client_id, rule)| (*client_id, rule.to_string()))
+                .collect(),
+            risk_rules: self
+                .risk_rules
+                .iter()
+                .map(|rule| (rule.name().to_string(), rule.state()))
+                .collect(),
+            client_risk_rules: HashMap::new(),
+        }
+    }
+
+    /// Snapshot with only sequence numbers, stores save the rest row by row.
+    ///
+    /// Risk rules are saved by their client states, their global state is left initial.
+    fn stored_state(&self) -> Snapshot {
+        Snapshot {
+            sequence: self.sequence,
+            changes: self.feed.sequence,
+            risk_rules: self
+                .risk_rules
+                .iter()
+                .map(|rule| (rule.name().to_string(), serde_json::Value::Null))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// State of client saved to store besides balances and transactions.
+    fn client_state(&self, client_id: ClientId) -> ClientState {
+        ClientState {
+            credit_limit: self
+                .config
+                .clients
+                .get(&client_id)
+                .map(|client_config| client_config.credit_limit),
+            withdrawal_history: self.withdrawal_history.get(&client_id).cloned(),
+            limit_breaches: self.limit_breaches.get(&client_id).copied(),
+            locked_by_rule: self
+                .locked_by_rule
+                .get(&client_id)
+                .map(|rule| rule.to_string()),
+            risk_rules: self
+                .risk_rules
+                .iter()
+                .map(|rule| (rule.name().to_string(), rule.client_state(client_id)))
+                .filter(|(_, state)| !state.is_null())
+                .collect(),
+        }
+    }
+
+    /// Process transactions whose account changes were published before restart without publishing them again.
+    ///
+    /// Change feed sequence advances as when they were published. Errors are ignored, as they were reported before.
+    #[cfg(feature = "kafka")]
+    pub(crate) fn replay(&mut self, transactions: impl IntoIterator<Item = Transaction>) {
+        self.feed.hold();
+        for transaction in transactions {
+            let _ = self.process_transaction(transaction);
+        }
+        self.feed.discard();
+    }
+
+    /// Replace state by snapshot of engine with the same config and risk rules.
+    pub(crate) fn restore(&mut self, mut snapshot: Snapshot) -> Result<(), EngineError> {
+        let rule_names: Vec<_> = self.risk_rules.iter().map(|rule| rule.name()).collect();
+        let rule_name = |name: &str| {
+            rule_names
+                .iter()
+                .copied()
+                .find(|rule_name| *rule_name == name)
+                .ok_or_else(|| EngineError::InvalidSnapshot(format!("unknown risk rule {}", name)))
+        };
+
+        let flagged = snapshot
+            .flagged
+            .iter()
+            .map(|(client, tx, rule)| {
+                Ok(FlaggedTransaction {
+                    client: *client,
+                    tx: *tx,
+                    rule: rule_name(rule)?,
+                })
+            })
+            .collect::<Result<_, EngineError>>()?;
+        let locked_by_rule = snapshot
+            .locked_by_rule
+            .iter()
+            .map(|(client_id, rule)| Ok((*client_id, rule_name(rule)?)))
+            .collect::<Result<_, EngineError>>()?;
+        for name in snapshot
+            .risk_rules
+            .keys()
+            .chain(snapshot.client_risk_rules.values().flat_map(HashMap::keys))
+        {
+            rule_name(name)?;
+        }
+        for rule in &mut self.risk_rules {
+            let state = snapshot.risk_rules.remove(rule.name()).ok_or_else(|| {
+                EngineError::InvalidSnapshot(format!("missing state of risk rule {}", rule.name()))
+            })?;
+            rule.restore(state)?;
+        }
+        for (client_id, mut states) in snapshot.client_risk_rules {
+            for rule in &mut self.risk_rules {
+                let state = states.remove(rule.name()).unwrap_or_default();
+                rule.restore_client(client_id, state)?;
+            }
+        }
+
+        let mut ledger = Ledger::default();
+        for posting in snapshot.postings {
+            ledger.post(posting.tx, posting.from, posting.to, posting.amount);
+        }
+        // Limits set after the snapshot was taken are reset as well
+        for (client_id, client_config) in &mut self.config.clients {
+            client_config.credit_limit =
+                snapshot.credit_limits.remove(client_id).unwrap_or_default();
+        }
+        for (client_id, credit_limit) in snapshot.credit_limits {
+            self.config
+                .clients
+                .entry(client_id)
+                .or_default()
+                .credit_limit = credit_limit;
+        }
+
+        self.sequence = snapshot.sequence;
+        self.feed.sequence = snapshot.changes;
+        self.clients = snapshot.clients;
+        self.withdrawal_history = snapshot.withdrawal_history;
+        self.limit_breaches = snapshot.limit_breaches;
+        self.flagged = flagged;
+        self.locked_by_rule = locked_by_rule;
+        self.ledger = ledger;
+        self.journal.clear();
+        self.feed.discard();
+
+        Ok(())
+    }
+
+    /// Keep data to roll back up to `limit` last transactions, 0 disables rollback.
+    pub(crate) fn set_rollback_limit(&mut self, limit: usize) {
+        self.journal.set_limit(limit);
+    }
+
+    /// Accept admin transactions such as `limit`, only for input of the operator.
+    pub(crate) fn allow_admin_transactions(&mut self) {
+        self.admin = true;
+    }
+
+    /// Name the current state, so that it can be restored by [`Engine::rollback_to`].
+    pub(crate) fn checkpoint(&mut self, name: &str) -> Result<(), EngineError> {
+        self.journal.checkpoint(name)
+    }
+
+    /// Revert the last `steps` processed transactions in reverse order, including rejected ones.
+    ///
+    /// Client balances, lock and dispute states, ledger and all other engine state end up exactly as before
+    /// the first reverted transaction. Change feed sequence keeps increasing: sent account changes are compensated
+    /// by new changes marked `rolled_back`, held changes of open batch are dropped.
+    pub(crate) fn rollback_steps(&mut self, steps: usize) -> Result<(), EngineError> {
+        // Transactions of open batch are not saved yet
+        if self.store.is_some() && !self.journal.in_batch() {
+            return Err(EngineError::Rollback(
+                "rollback is not supported with database".to_string(),
+            ));
+        }
+
+        let steps = self.journal.pop(steps)?;
+        let Some(changes) = steps.last().map(|step| step.changes) else {
+            return Ok(());
+        };
+        if self.journal.in_batch() {
+            self.revert_all(steps)?;
+            self.feed.retract(changes);
+            return Ok(());
+        }
+
+        // Oldest reverted transaction of each client with account before rollback
+        let mut reverted: Vec<(ClientId, TransactionId, TransactionType, _)> = Vec::new();
+        for step in steps.iter().rev() {
+            if !reverted
+                .iter()
+                .any(|(client_id, ..)| *client_id == step.client_id)
+            {
+                let account = self.account(step.client_id);
+                reverted.push((step.client_id, step.tx, step.tx_type, account));
+            }
+        }
+        self.revert_all(steps)?;
+
+        for (client_id, tx, tx_type, before) in reverted {
+            let (available, held, locked) = self.account(client_id);
+            if (available, held, locked) != before {
+                self.feed.publish(AccountChanged {
+                    sequence: 0,
+                    client: client_id,
+                    tx,
+                    tx_type,
+                    available,
+                    held,
+                    total: available + held,
+                    locked,
+                    rolled_back: true,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Available, held and locked of client, zero and unlocked for unknown client.
+    fn account(&self, client_id: ClientId) -> (Amount, Amount, bool) {
+        self.clients
+            .get(&client_id)
+            .map_or((Decimal::ZERO, Decimal::ZERO, false), |client| {
+                (client.available, client.held, client.locked)
+            })
+    }
+
+    /// Revert all transactions processed since checkpoint `name`, returns their number.
+    pub(crate) fn rollback_to(&mut self, name: &str) -> Result<usize, EngineError> {
+        let steps = self.journal.steps_since(name)?;
+        self.rollback_steps(steps)?;
+        Ok(steps)
+    }
+
+    /// Start batch of transactions applied all or nothing by [`Engine::commit`] or [`Engine::rollback`].
+    ///
+    /// Transactions of batch are processed as usual, but their account changes are sent to change feed sinks
+    /// and saved to store only on commit. Batch can be rolled back regardless of rollback limit.
+    pub(crate) fn begin(&mut self) -> Result<(), EngineError> {
+        self.journal.begin()?;
+        self.feed.hold();
+        Ok(())
+    }
+
+    /// Make transactions of open batch permanent, all of them are saved in one database transaction.
+    ///
+    /// When saving fails the batch is rolled back.
+    pub(crate) fn commit(&mut self) -> Result<(), EngineError> {
+        let touched: Vec<_> = self
+            .journal
+            .batch_changes()?
+            .into_iter()
+            .map(|(client_id, tx)| (client_id, Some(tx)))
+            .collect();
+        if let Err(err) = self.save(&touched) {
+            self.rollback()?;
+            return Err(err);
+        }
+
+        self.journal.commit()?;
+        self.feed.release();
+        Ok(())
+    }
+
+    /// Revert all transactions of open batch, their account changes are never published.
+    pub(crate) fn rollback(&mut self) -> Result<(), EngineError> {
+        let steps = self.journal.abort()?;
+        if let Some(step) = steps.last() {
+            self.feed.retract(step.changes);
+        }
+        self.revert_all(steps)?;
+        self.feed.discard();
+        Ok(())
+    }
+
+    /// Revert steps ordered from the newest.
+    fn revert_all(&mut self, steps: Vec<UndoStep>) -> Result<(), EngineError> {
+        let Some(oldest) = steps.last() else {
+            return Ok(());
+        };
+        self.ledger.truncate(oldest.postings);
+        self.flagged.truncate(oldest.flagged);
+        for step in steps {
+            self.revert(step)?;
+        }
+        Ok(())
+    }
+
+    fn undo_step(&self, transaction: &Transaction) -> UndoStep {
+        let client_id = transaction.client;
+        let client = self.clients.get(&client_id);
+
+        UndoStep {
+            sequence: self.sequence,
+            changes: self.feed.sequence,
+            postings: self.ledger.postings().len(),
+            flagged: self.flagged.len(),
+            client_id,
+            tx: transaction.tx,
+            tx_type: transaction.tx_type,
+            balances: client.map(|client| (client.available, client.held, client.locked)),
+            transaction: client
+                .and_then(|client| client.transactions.get(&transaction.tx).cloned()),
+            credit_limit: self
+                .config
+                .clients
+                .get(&client_id)
+                .map(|client_config| client_config.credit_limit),
+            withdrawal_history: self.withdrawal_history.get(&client_id).cloned(),
+            limit_breaches: self.limit_breaches.get(&client_id).copied(),
+            locked_by_rule: self.locked_by_rule.get(&client_id).copied(),
+            risk_rules: self
+                .risk_rules
+                .iter()
+                .map(|rule| rule.client_state(client_id))
+                .collect(),
+        }
+    }
+
+    /// Restore state of client and counters from step, ledger and flagged transactions are truncated by caller.
+    fn revert(&mut self, step: UndoStep) -> Result<(), EngineError> {
+        let client_id = step.client_id;
+        self.sequence = step.sequence;
+
+        match step.balances {
+            Some((available, held, locked)) => {
+                let client = self.clients.entry(client_id).or_default();
+                (client.available, client.held, client.locked) = (available, held, locked);
+                match step.transaction {
+                    Some(transaction) => client.transactions.insert(step.tx, transaction),
+                    None => client.transactions.remove(&step.tx),
+                };
+            }
+            None => {
+                self.clients.remove(&client_id);
+            }
+        }
+
+        if step.tx_type == TransactionType::Limit {
+            match step.credit_limit {
+                Some(credit_limit) => {
+                    self.config
+                        .clients
+                        .entry(client_id)
+                        .or_default()
+                        .credit_limit = credit_limit
+                }
+                None => {
+                    self.config.clients.remove(&client_id);
+                }
+            }
+        }
+
+        restore_entry(
+            &mut self.withdrawal_history,
+            client_id,
+            step.withdrawal_history,
+        );
+        restore_entry(&mut self.limit_breaches, client_id, step.limit_breaches);
+        restore_entry(&mut self.locked_by_rule, client_id, step.locked_by_rule);
+        for (rule, state) in self.risk_rules.iter_mut().zip(step.risk_rules) {
+            rule.restore_client(client_id, state)?;
+        }
+        Ok(())
+    }
+
+    /// Total fees charged to clients, reduced by reversed fees.
+    fn client_fees(&self) -> &HashMap<ClientId, Amount> {
+        self.ledger.client_fees()
+    }
+
+    fn house_balance(&self) -> Amount {
+        self.ledger.balance(Account::House)
+    }
+
+    /// Process all types of transactions, risk rules are evaluated before client state is changed.
+    ///
+    /// With store the changes are saved in one database transaction before returning, or on commit of open batch.
+    /// Transaction whose changes cannot be saved is reverted, so that engine does not get ahead of the database.
+    pub(crate) fn process_transaction(
+        &mut self,
+        transaction: Transaction,
+    ) -> Result<TransactionOutcome, EngineError> {
+        if self.store.is_some() && !self.journal.in_batch() {
+            // Batch of one holds back account changes and is rolled back when saving fails
+            self.begin()?;
+            let result = self.process_transaction(transaction);
+            self.commit()?;
+            return result;
+        }
+
+        let step = self
+            .journal
+            .is_enabled()
+            .then(|| self.undo_step(&transaction));
+        let result = self.process(transaction);
+        if let Some(step) = step {
+            self.journal.push(step);
+        }
+        result
+    }
+
+    /// Save given clients with their transaction, if any, and new postings and flagged transactions to store.
+    fn save(&mut self, touched: &[(ClientId, Option<TransactionId>)]) -> Result<(), EngineError> {
+        let Some(mut store) = self.store.take() else {
+            return Ok(());
+        };
+
+        let updates = touched
+            .iter()
+            .map(|(client_id, tx)| {
+                let client = self.clients.get(client_id);
+                Update {
+                    client_id: *client_id,
+                    client,
+                    transaction: tx.and_then(|tx| {
+                        client
+                            .and_then(|client| client.transactions.get(&tx))
+                            .map(|stored| (tx, stored))
+                    }),
+                    state: self.client_state(*client_id),
+                }
+            })
+            .collect();
+        let saved = store.save(&Changes {
+            updates,
+            postings: self.ledger.postings(),
+            flagged: &self.flagged,
+            state: &self.stored_state(),
+        });
+        self.store = Some(store);
+        saved
+    }
+
+    fn process(&mut self, transaction: Transaction) -> Result<TransactionOutcome, EngineError> {
+        self.sequence += 1;
+
+        if transaction.tx_type == TransactionType::Limit {
+            if !self.admin {
+                return Err(EngineError::AdminTransaction(transaction.tx));
+            }
+            if self
+                .clients
+                .get(&transaction.client)
+                .is_some_and(|client| client.locked)
+            {
+                return Ok(TransactionOutcome::Rejected(RejectReason::AccountLocked));
+            }
+            let limit = transaction.get_limit()?;
+            self.config
+                .clients
+                .entry(transaction.client)
+                .or_default()
+                .credit_limit = limit;
+            return Ok(TransactionOutcome::Applied);
+        }
+
+        let before = self.account(transaction.client);
         let client = self.clients.entry(transaction.client).or_default();
 
         if client.locked {
-            return Ok(());
+            return Ok(TransactionOutcome::Rejected(RejectReason::AccountLocked));
         }
 
+        let mut flags = Vec::new();
+        let mut lock = None;
+        for rule in &mut self.risk_rules {
+            match rule.evaluate(&transaction, client) {
+                RiskDecision::Allow => {}
+                RiskDecision::Reject => {
+                    return Ok(TransactionOutcome::Rejected(RejectReason::RiskRule(
+                        rule.name(),
+                    )))
+                }
+                RiskDecision::Flag => flags.push(rule.name()),
+                RiskDecision::Lock => lock = lock.or(Some(rule.name())),
+            }
+        }
+
+        let (client_id, tx, tx_type) = (transaction.client, transaction.tx, transaction.tx_type);
+        let outcome = self.apply_transaction(transaction)?;
+
+        if outcome == TransactionOutcome::Applied {
+            self.flagged
+                .extend(flags.into_iter().map(|rule| FlaggedTransaction {
+                    client: client_id,
+                    tx,
+                    rule,
+                }));
+        }
+        if let Some(rule) = lock {
+            self.clients.entry(client_id).or_default().locked = true;
+            self.locked_by_rule.entry(client_id).or_insert(rule);
+        }
+
+        let client = &self.clients[&client_id];
+        if (client.available, client.held, client.locked) != before {
+            self.feed.publish(AccountChanged {
+                sequence: 0,
+                client: client_id,
+                tx,
+                tx_type,
+                available: client.available,
+                held: client.held,
+                total: client.available + client.held,
+                locked: client.locked,
+                rolled_back: false,
+            });
+        }
+
+        if self.invariant_check == InvariantCheck::Each {
+            let violations = self.check_client_invariants(client_id);
+            if !violations.is_empty() {
+                return Err(EngineError::InvariantViolated(format!(
+                    "after transaction with id {}: {}",
+                    tx,
+                    describe(&violations)
+                )));
+            }
+        }
+
+        Ok(outcome)
+    }
+
+    /// Change client state according to transaction.
+    ///
+    /// Amounts are moved only by ledger postings, client `available` and `held` are synced from ledger afterwards.
+    ///
+    /// Note: Not splitting processing of particular transaction types into separate functions as actual processing is quite simple.
+    fn apply_transaction(
+        &mut self,
+        transaction: Transaction,
+    ) -> Result<TransactionOutcome, EngineError> {
+        let credit_limit = self.config.credit_limit(transaction.client);
+        let fees = self.config.fees(transaction.client);
+        let client = self.clients.entry(transaction.client).or_default();
+
+        let available = Account::Available(transaction.client);
+        let held = Account::Held(transaction.client);
+
         match transaction.tx_type {
             TransactionType::Deposit => {
                 let amount = transaction.get_amount()?;
+                let fee = fees.deposit_fee(amount);
+
+                self.ledger
+                    .post(transaction.tx, Account::Settlement, available, amount);
+                if !fee.is_zero() {
+                    self.ledger
+                        .post(transaction.tx, available, Account::House, fee);
+                }
 
-                client.available += amount;
                 client.transactions.insert(
                     transaction.tx,
                     StoredTransaction::new(transaction.tx_type, amount),
@@ -66,62 +886,153 @@ impl Engine {
             TransactionType::Withdrawal => {
                 let amount = transaction.get_amount()?;
 
-                if client.available >= amount {
-                    client.available -= amount;
-                    client.transactions.insert(
-                        transaction.tx,
-                        StoredTransaction::new(transaction.tx_type, amount),
-                    );
+                let limits = self.config.withdrawal_limits(transaction.client);
+                let mut history = (!limits.is_empty()).then(|| {
+                    self.withdrawal_history
+                        .entry(transaction.client)
+                        .or_default()
+                });
+
+                if let Some(history) = &mut history {
+                    if let Err(reason) = history.check(&limits, self.sequence, amount) {
+                        *self.limit_breaches.entry(transaction.client).or_default() += 1;
+                        return Ok(TransactionOutcome::Rejected(reason));
+                    }
+                }
+
+                let fee = fees.withdrawal_fee();
+
+                if client.available + credit_limit < amount + fee {
+                    return Ok(TransactionOutcome::Rejected(if credit_limit.is_zero() {
+                        RejectReason::InsufficientFunds
+                    } else {
+                        RejectReason::CreditLimitExceeded
+                    }));
+                }
+
+                self.ledger
+                    .post(transaction.tx, available, Account::Settlement, amount);
+                if !fee.is_zero() {
+                    self.ledger
+                        .post(transaction.tx, available, Account::House, fee);
+                }
+
+                client.transactions.insert(
+                    transaction.tx,
+                    StoredTransaction::new(transaction.tx_type, amount),
+                );
+
+                if let Some(history) = history {
+                    history.record(self.sequence, amount);
                 }
             }
             TransactionType::Dispute => {
-                if let Some(disputed_trans) = client.transactions.get_mut(&transaction.tx) {
-                    if disputed_trans.dispute_state == DisputeState::None {
-                        disputed_trans.dispute_state = DisputeState::Open;
-
-                        if disputed_trans.tx_type == TransactionType::Deposit {
-                            client.available -= disputed_trans.amount;
-                            client.held += disputed_trans.amount;
-                        }
-                    }
+                let Some(disputed_trans) = client.transactions.get_mut(&transaction.tx) else {
+                    return Ok(TransactionOutcome::Rejected(
+                        RejectReason::UnknownTransaction,
+                    ));
+                };
+                if disputed_trans.dispute_state != DisputeState::None {
+                    return Ok(TransactionOutcome::Rejected(
+                        RejectReason::InvalidDisputeState,
+                    ));
+                }
+
+                disputed_trans.dispute_state = DisputeState::Open;
+
+                if disputed_trans.tx_type == TransactionType::Deposit {
+                    self.ledger
+                        .post(transaction.tx, available, held, disputed_trans.amount);
                 }
             }
             TransactionType::Resolve => {
-                if let Some(disputed_trans) = client.transactions.get_mut(&transaction.tx) {
-                    if disputed_trans.dispute_state == DisputeState::Open {
-                        disputed_trans.dispute_state = DisputeState::None;
-
-                        if disputed_trans.tx_type == TransactionType::Deposit {
-                            client.available += disputed_trans.amount;
-                            client.held -= disputed_trans.amount;
-                        }
-                    }
+                let Some(disputed_trans) = client.transactions.get_mut(&transaction.tx) else {
+                    return Ok(TransactionOutcome::Rejected(
+                        RejectReason::UnknownTransaction,
+                    ));
+                };
+                if disputed_trans.dispute_state != DisputeState::Open {
+                    return Ok(TransactionOutcome::Rejected(
+                        RejectReason::InvalidDisputeState,
+                    ));
+                }
+
+                disputed_trans.dispute_state = DisputeState::None;
+
+                if disputed_trans.tx_type == TransactionType::Deposit {
+                    self.ledger
+                        .post(transaction.tx, held, available, disputed_trans.amount);
                 }
             }
             TransactionType::Chargeback => {
-                if let Some(disputed_trans) = client.transactions.get_mut(&transaction.tx) {
-                    if disputed_trans.dispute_state == DisputeState::Open {
-                        disputed_trans.dispute_state = DisputeState::Chargeback;
-                        client.locked = true;
-
-                        match disputed_trans.tx_type {
-                            TransactionType::Deposit => client.held -= disputed_trans.amount,
-                            TransactionType::Withdrawal => {
-                                client.available += disputed_trans.amount
-                            }
-                            TransactionType::Dispute
-                            | TransactionType::Resolve
-                            | TransactionType::Chargeback => panic!("Cannot get here"),
-                        }
+                let Some(disputed_trans) = client.transactions.get_mut(&transaction.tx) else {
+                    return Ok(TransactionOutcome::Rejected(
+                        RejectReason::UnknownTransaction,
+                    ));
+                };
+                if disputed_trans.dispute_state != DisputeState::Open {
+                    return Ok(TransactionOutcome::Rejected(
+                        RejectReason::InvalidDisputeState,
+                    ));
+                }
+
+                disputed_trans.dispute_state = DisputeState::Chargeback;
+                client.locked = true;
+
+                match disputed_trans.tx_type {
+                    TransactionType::Deposit => self.ledger.post(
+                        transaction.tx,
+                        held,
+                        Account::Settlement,
+                        disputed_trans.amount,
+                    ),
+                    TransactionType::Withdrawal => self.ledger.post(
+                        transaction.tx,
+                        Account::Settlement,
+                        available,
+                        disputed_trans.amount,
+                    ),
+                    TransactionType::Dispute
+                    | TransactionType::Resolve
+                    | TransactionType::Chargeback
+                    | TransactionType::Limit => panic!("Cannot get here"),
+                }
+
+                if fees.reverse_on_chargeback == Some(true) {
+                    let charged_fee = self.ledger.charged_fee(transaction.client, transaction.tx);
+
+                    if !charged_fee.is_zero() {
+                        self.ledger
+                            .post(transaction.tx, Account::House, available, charged_fee);
                     }
                 }
             }
+            TransactionType::Limit => panic!("Cannot get here"),
         }
 
-        Ok(())
+        client.available = self.ledger.balance(available);
+        client.held = self.ledger.balance(held);
+
+        Ok(TransactionOutcome::Applied)
     }
 }
 
+fn describe(violations: &[InvariantViolation]) -> String {
+    violations
+        .iter()
+        .map(|violation| violation.to_string())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Set entry of `client_id` to value it had before, `None` when it was missing.
+fn restore_entry<V>(map: &mut HashMap<ClientId, V>, client_id: ClientId, value: Option<V>) {
+    match value {
+        Some(value) => map.insert(client_id, value),
+        None => map.remove(&client_id),
+    };
+}
+
 #[cfg(test)]
 #[path = "engine.test.rs"]
 mod tests;
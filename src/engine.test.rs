@@ -4,8 +4,20 @@ use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
 
 use crate::{
-    engine::Engine,
-    types::{Client, DisputeState, StoredTransaction, Transaction, TransactionType},
+    cli::{exit_code, EXIT_PARSE},
+    config::EngineConfig,
+    dialect::{Column, ColumnMapping, CsvDialect},
+    engine::{Engine, FlaggedTransaction},
+    input::InputOptions,
+    invariants::InvariantCheck,
+    ledger::Account,
+    output::ClientBalance,
+    risk::{RiskDecision, RiskRule},
+    testing::{process, temp_path},
+    types::{
+        Client, DisputeState, EngineError, RejectReason, StoredTransaction, Transaction,
+        TransactionOutcome, TransactionType,
+    },
 };
 
 #[test]
@@ -1793,7 +1805,787 @@ fn test_chargeback_withdrawal() {
     test_transactions!(transactions_and_clients);
 }
 
+#[test]
+fn test_rejected_transactions() {
+    let mut engine = Engine::new();
+
+    let transactions_and_outcomes = [
+        (
+            Transaction {
+                tx_type: TransactionType::Withdrawal,
+                client: 1,
+                tx: 1,
+                amount: Some(dec!(1.5)),
+            },
+            TransactionOutcome::Rejected(RejectReason::InsufficientFunds),
+        ),
+        (
+            Transaction {
+                tx_type: TransactionType::Dispute,
+                client: 1,
+                tx: 1,
+                amount: None,
+            },
+            TransactionOutcome::Rejected(RejectReason::UnknownTransaction),
+        ),
+        (
+            Transaction {
+                tx_type: TransactionType::Deposit,
+                client: 1,
+                tx: 2,
+                amount: Some(dec!(1.5)),
+            },
+            TransactionOutcome::Applied,
+        ),
+        (
+            Transaction {
+                tx_type: TransactionType::Resolve,
+                client: 1,
+                tx: 2,
+                amount: None,
+            },
+            TransactionOutcome::Rejected(RejectReason::InvalidDisputeState),
+        ),
+        (
+            Transaction {
+                tx_type: TransactionType::Dispute,
+                client: 1,
+                tx: 2,
+                amount: None,
+            },
+            TransactionOutcome::Applied,
+        ),
+        (
+            Transaction {
+                tx_type: TransactionType::Chargeback,
+                client: 1,
+                tx: 2,
+                amount: None,
+            },
+            TransactionOutcome::Applied,
+        ),
+        (
+            Transaction {
+                tx_type: TransactionType::Deposit,
+                client: 1,
+                tx: 3,
+                amount: Some(dec!(1.5)),
+            },
+            TransactionOutcome::Rejected(RejectReason::AccountLocked),
+        ),
+    ];
+
+    for (trans, outcome) in transactions_and_outcomes {
+        assert_eq!(engine.process_transaction(trans).unwrap(), outcome);
+    }
+}
+
+#[test]
+fn test_credit_limit_from_config() {
+    let config: EngineConfig = toml::from_str(
+        r#"
+        [clients.1]
+        credit_limit = "1.0"
+        "#,
+    )
+    .unwrap();
+    let mut engine = Engine::with_config(config);
+
+    let withdrawal_within_limit = Transaction {
+        tx_type: TransactionType::Withdrawal,
+        client: 1,
+        tx: 1,
+        amount: Some(dec!(0.6)),
+    };
+    assert_eq!(
+        engine.process_transaction(withdrawal_within_limit).unwrap(),
+        TransactionOutcome::Applied
+    );
+
+    let withdrawal_over_limit = Transaction {
+        tx_type: TransactionType::Withdrawal,
+        client: 1,
+        tx: 2,
+        amount: Some(dec!(0.6)),
+    };
+    assert_eq!(
+        engine.process_transaction(withdrawal_over_limit).unwrap(),
+        TransactionOutcome::Rejected(RejectReason::CreditLimitExceeded)
+    );
+
+    let withdrawal_to_limit = Transaction {
+        tx_type: TransactionType::Withdrawal,
+        client: 1,
+        tx: 3,
+        amount: Some(dec!(0.4)),
+    };
+    assert_eq!(
+        engine.process_transaction(withdrawal_to_limit).unwrap(),
+        TransactionOutcome::Applied
+    );
+
+    assert_eq!(engine.clients[&1].available, dec!(-1.0));
+}
+
+#[test]
+fn test_credit_limit_admin_transaction() {
+    let mut engine = Engine::new();
+    engine.allow_admin_transactions();
+
+    let set_limit = Transaction {
+        tx_type: TransactionType::Limit,
+        client: 1,
+        tx: 1,
+        amount: Some(dec!(2.0)),
+    };
+    assert_eq!(
+        engine.process_transaction(set_limit).unwrap(),
+        TransactionOutcome::Applied
+    );
+    assert!(engine.clients.is_empty());
+    assert_eq!(engine.config.credit_limit(1), dec!(2.0));
+
+    let withdrawal = Transaction {
+        tx_type: TransactionType::Withdrawal,
+        client: 1,
+        tx: 2,
+        amount: Some(dec!(1.5)),
+    };
+    assert_eq!(
+        engine.process_transaction(withdrawal).unwrap(),
+        TransactionOutcome::Applied
+    );
+
+    let remove_limit = Transaction {
+        tx_type: TransactionType::Limit,
+        client: 1,
+        tx: 3,
+        amount: Some(Decimal::ZERO),
+    };
+    assert_eq!(
+        engine.process_transaction(remove_limit).unwrap(),
+        TransactionOutcome::Applied
+    );
+
+    let withdrawal = Transaction {
+        tx_type: TransactionType::Withdrawal,
+        client: 1,
+        tx: 4,
+        amount: Some(dec!(0.1)),
+    };
+    assert_eq!(
+        engine.process_transaction(withdrawal).unwrap(),
+        TransactionOutcome::Rejected(RejectReason::InsufficientFunds)
+    );
+    assert_eq!(engine.clients[&1].available, dec!(-1.5));
+}
+
+#[test]
+#[should_panic(expected = "AmountNegative(-1)")]
+fn test_credit_limit_must_not_be_negative() {
+    let mut engine = Engine::new();
+    engine.allow_admin_transactions();
+
+    let set_limit = Transaction {
+        tx_type: TransactionType::Limit,
+        client: 1,
+        tx: 1,
+        amount: Some(dec!(-1)),
+    };
+
+    engine.process_transaction(set_limit).unwrap();
+}
+
+#[test]
+fn test_admin_transaction_in_input_file_is_refused() {
+    let path = temp_path("admin.csv");
+    std::fs::write(&path, "type,client,tx,amount\nlimit,1,1,5.0\n").unwrap();
+    let files = [path.to_str().unwrap().to_string()];
+
+    let mut engine = Engine::new();
+    let err = engine
+        .read_and_process_input(&files, &InputOptions::default())
+        .unwrap_err();
+    assert!(matches!(err, EngineError::AdminTransaction(1)), "{:?}", err);
+    assert_eq!(exit_code(&err), EXIT_PARSE);
+    assert_eq!(engine.config.credit_limit(1), Decimal::ZERO);
+
+    let mut engine = Engine::new();
+    engine.allow_admin_transactions();
+    engine
+        .read_and_process_input(&files, &InputOptions::default())
+        .unwrap();
+    assert_eq!(engine.config.credit_limit(1), dec!(5.0));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_admin_transaction_is_rejected_for_locked_client() {
+    let mut engine = Engine::new();
+    engine.allow_admin_transactions();
+    process(
+        &mut engine,
+        &["deposit, 1, 1, 1.0", "dispute, 1, 1,", "chargeback, 1, 1,"],
+    );
+
+    let set_limit = Transaction {
+        tx_type: TransactionType::Limit,
+        client: 1,
+        tx: 2,
+        amount: Some(dec!(2.0)),
+    };
+    assert_eq!(
+        engine.process_transaction(set_limit).unwrap(),
+        TransactionOutcome::Rejected(RejectReason::AccountLocked)
+    );
+    assert_eq!(engine.config.credit_limit(1), Decimal::ZERO);
+}
+
+#[test]
+fn test_withdrawal_limits() {
+    let config: EngineConfig = toml::from_str(
+        r#"
+        [withdrawal_limits]
+        max_amount = "5.0"
+        max_total = "6.5"
+        max_count = 2
+        window = 4
+
+        [clients.2]
+        withdrawal_limits = { max_amount = "10.0", max_total = "20.0" }
+        "#,
+    )
+    .unwrap();
+    let mut engine = Engine::with_config(config);
+
+    let withdrawal = |client, tx, amount| Transaction {
+        tx_type: TransactionType::Withdrawal,
+        client,
+        tx,
+        amount: Some(amount),
+    };
+
+    let transactions_and_outcomes = [
+        (
+            Transaction {
+                tx_type: TransactionType::Deposit,
+                client: 1,
+                tx: 1,
+                amount: Some(dec!(100)),
+            },
+            TransactionOutcome::Applied,
+        ),
+        (
+            withdrawal(1, 2, dec!(5.1)),
+            TransactionOutcome::Rejected(RejectReason::WithdrawalAmountExceeded),
+        ),
+        (withdrawal(1, 3, dec!(5.0)), TransactionOutcome::Applied),
+        (
+            withdrawal(1, 4, dec!(1.6)),
+            TransactionOutcome::Rejected(RejectReason::WithdrawalTotalExceeded),
+        ),
+        (withdrawal(1, 5, dec!(1.0)), TransactionOutcome::Applied),
+        (
+            withdrawal(1, 6, dec!(0.1)),
+            TransactionOutcome::Rejected(RejectReason::WithdrawalCountExceeded),
+        ),
+        // withdrawal tx 3 dropped out of the window
+        (withdrawal(1, 7, dec!(0.1)), TransactionOutcome::Applied),
+        // client 2 overrides max_amount and max_total, inherits max_count
+        (
+            Transaction {
+                tx_type: TransactionType::Deposit,
+                client: 2,
+                tx: 8,
+                amount: Some(dec!(100)),
+            },
+            TransactionOutcome::Applied,
+        ),
+        (withdrawal(2, 9, dec!(7.0)), TransactionOutcome::Applied),
+        (withdrawal(2, 10, dec!(3.0)), TransactionOutcome::Applied),
+        (
+            withdrawal(2, 11, dec!(0.1)),
+            TransactionOutcome::Rejected(RejectReason::WithdrawalCountExceeded),
+        ),
+    ];
+
+    for (trans, outcome) in transactions_and_outcomes {
+        assert_eq!(engine.process_transaction(trans).unwrap(), outcome);
+    }
+
+    assert_eq!(engine.clients[&1].available, dec!(93.9));
+    assert_eq!(engine.clients[&2].available, dec!(90.0));
+    assert_eq!(engine.limit_breaches, HashMap::from([(1, 3), (2, 1)]));
+}
+
+#[test]
+fn test_builtin_risk_rules() {
+    let config: EngineConfig = toml::from_str(
+        r#"
+        [risk_rules]
+        immediate_withdrawal = "flag"
+        lock_on_open_disputes = 2
+        "#,
+    )
+    .unwrap();
+    let mut engine = Engine::with_config(config);
+
+    let transactions = [
+        (TransactionType::Deposit, 1, Some(dec!(5))),
+        (TransactionType::Withdrawal, 2, Some(dec!(5))),
+        (TransactionType::Deposit, 3, Some(dec!(5))),
+        (TransactionType::Deposit, 4, Some(dec!(5))),
+        (TransactionType::Dispute, 3, None),
+        (TransactionType::Dispute, 4, None),
+    ];
+
+    for (tx_type, tx, amount) in transactions {
+        let trans = Transaction {
+            tx_type,
+            client: 1,
+            tx,
+            amount,
+        };
+        assert_eq!(
+            engine.process_transaction(trans).unwrap(),
+            TransactionOutcome::Applied
+        );
+    }
+
+    assert_eq!(
+        engine.flagged,
+        vec![FlaggedTransaction {
+            client: 1,
+            tx: 2,
+            rule: "immediate_withdrawal",
+        }]
+    );
+    assert!(engine.clients[&1].locked);
+    assert_eq!(engine.clients[&1].held, dec!(10));
+}
+
+#[test]
+fn test_custom_risk_rule() {
+    /// Rejects deposits over 100
+    struct LargeDepositRule;
+
+    impl RiskRule for LargeDepositRule {
+        fn name(&self) -> &'static str {
+            "large_deposit"
+        }
+
+        fn evaluate(&mut self, transaction: &Transaction, _client: &Client) -> RiskDecision {
+            match (&transaction.tx_type, transaction.amount) {
+                (TransactionType::Deposit, Some(amount)) if amount > dec!(100) => {
+                    RiskDecision::Reject
+                }
+                _ => RiskDecision::Allow,
+            }
+        }
+    }
+
+    let mut engine =
+        Engine::with_risk_rules(EngineConfig::default(), vec![Box::new(LargeDepositRule)]);
+
+    let large_deposit = Transaction {
+        tx_type: TransactionType::Deposit,
+        client: 1,
+        tx: 1,
+        amount: Some(dec!(100.01)),
+    };
+    assert_eq!(
+        engine.process_transaction(large_deposit).unwrap(),
+        TransactionOutcome::Rejected(RejectReason::RiskRule("large_deposit"))
+    );
+    assert_eq!(engine.clients[&1], Client::default());
+
+    let deposit = Transaction {
+        tx_type: TransactionType::Deposit,
+        client: 1,
+        tx: 2,
+        amount: Some(dec!(100)),
+    };
+    assert_eq!(
+        engine.process_transaction(deposit).unwrap(),
+        TransactionOutcome::Applied
+    );
+}
+
+#[test]
+fn test_fees() {
+    let config: EngineConfig = toml::from_str(
+        r#"
+        [fees]
+        withdrawal_flat = "0.5"
+        deposit_percent = "1"
+        deposit_min = "0.1"
+        deposit_cap = "2"
+        reverse_on_chargeback = true
+
+        [clients.2]
+        fees = { withdrawal_flat = "0", reverse_on_chargeback = false }
+        "#,
+    )
+    .unwrap();
+    let mut engine = Engine::with_config(config);
+
+    let transactions_and_outcomes = [
+        // fee 1 % of deposit
+        (
+            (TransactionType::Deposit, 1, 1, Some(dec!(50))),
+            TransactionOutcome::Applied,
+        ),
+        // minimal fee
+        (
+            (TransactionType::Deposit, 1, 2, Some(dec!(5))),
+            TransactionOutcome::Applied,
+        ),
+        // capped fee
+        (
+            (TransactionType::Deposit, 1, 3, Some(dec!(1000))),
+            TransactionOutcome::Applied,
+        ),
+        // available 1052.4, withdrawal fee does not fit
+        (
+            (TransactionType::Withdrawal, 1, 4, Some(dec!(1052))),
+            TransactionOutcome::Rejected(RejectReason::InsufficientFunds),
+        ),
+        (
+            (TransactionType::Withdrawal, 1, 5, Some(dec!(1051.9))),
+            TransactionOutcome::Applied,
+        ),
+        // deposit fee is reversed on chargeback
+        (
+            (TransactionType::Dispute, 1, 2, None),
+            TransactionOutcome::Applied,
+        ),
+        (
+            (TransactionType::Chargeback, 1, 2, None),
+            TransactionOutcome::Applied,
+        ),
+        // client 2 has free withdrawals and fees are not reversed
+        (
+            (TransactionType::Deposit, 2, 6, Some(dec!(20))),
+            TransactionOutcome::Applied,
+        ),
+        (
+            (TransactionType::Withdrawal, 2, 7, Some(dec!(10))),
+            TransactionOutcome::Applied,
+        ),
+        (
+            (TransactionType::Dispute, 2, 6, None),
+            TransactionOutcome::Applied,
+        ),
+        (
+            (TransactionType::Chargeback, 2, 6, None),
+            TransactionOutcome::Applied,
+        ),
+    ];
+
+    for ((tx_type, client, tx, amount), outcome) in transactions_and_outcomes {
+        let trans = Transaction {
+            tx_type,
+            client,
+            tx,
+            amount,
+        };
+        assert_eq!(engine.process_transaction(trans).unwrap(), outcome);
+    }
+
+    assert_eq!(engine.clients[&1].available, dec!(-4.9));
+    assert_eq!(engine.clients[&2].available, dec!(-10.2));
+    assert_eq!(
+        engine.client_fees(),
+        &HashMap::from([(1, dec!(3.0)), (2, dec!(0.2))])
+    );
+    assert_eq!(engine.house_balance(), dec!(3.2));
+}
+
+#[test]
+fn test_minimal_fee_of_small_deposit() {
+    let config: EngineConfig = toml::from_str(
+        r#"
+        [fees]
+        deposit_percent = "1"
+        deposit_min = "1"
+        "#,
+    )
+    .unwrap();
+    let mut engine = Engine::with_config(config);
+
+    let deposit = Transaction {
+        tx_type: TransactionType::Deposit,
+        client: 1,
+        tx: 1,
+        amount: Some(dec!(0.5)),
+    };
+    assert_eq!(
+        engine.process_transaction(deposit).unwrap(),
+        TransactionOutcome::Applied
+    );
+
+    // Fee takes the whole deposit, balance does not go negative
+    assert_eq!(engine.clients[&1].available, dec!(0));
+    assert_eq!(engine.house_balance(), dec!(0.5));
+    engine.verify_ledger().unwrap();
+}
+
+#[test]
+fn test_ledger() {
+    let config: EngineConfig = toml::from_str(
+        r#"
+        [fees]
+        withdrawal_flat = "0.1"
+        "#,
+    )
+    .unwrap();
+    let mut engine = Engine::with_config(config);
+
+    let transactions = [
+        (TransactionType::Deposit, 1, 1, Some(dec!(10))),
+        (TransactionType::Withdrawal, 1, 2, Some(dec!(3))),
+        (TransactionType::Deposit, 2, 3, Some(dec!(5))),
+        (TransactionType::Dispute, 2, 3, None),
+        (TransactionType::Deposit, 3, 4, Some(dec!(7))),
+        (TransactionType::Dispute, 3, 4, None),
+        (TransactionType::Chargeback, 3, 4, None),
+    ];
+
+    for (tx_type, client, tx, amount) in transactions {
+        let trans = Transaction {
+            tx_type,
+            client,
+            tx,
+            amount,
+        };
+        assert_eq!(
+            engine.process_transaction(trans).unwrap(),
+            TransactionOutcome::Applied
+        );
+    }
+
+    assert!(engine.verify_ledger().is_ok());
+    assert_eq!(
+        engine.ledger.trial_balance(),
+        vec![
+            (Account::Available(1), dec!(6.9)),
+            (Account::Available(2), dec!(0)),
+            (Account::Available(3), dec!(0)),
+            (Account::Held(2), dec!(5)),
+            (Account::Held(3), dec!(0)),
+            (Account::Settlement, dec!(-12)),
+            (Account::House, dec!(0.1)),
+        ]
+    );
+    assert_eq!(engine.clients[&1].available, dec!(6.9));
+    assert_eq!(engine.clients[&2].held, dec!(5));
+
+    // client amounts changed outside of ledger are detected
+    engine.clients.get_mut(&1).unwrap().available += dec!(1);
+    assert!(engine.verify_ledger().is_err());
+}
+
+#[test]
+fn test_check_invariants_after_each_transaction() {
+    let config: EngineConfig = toml::from_str(
+        r#"
+        [risk_rules]
+        lock_on_open_disputes = 1
+        "#,
+    )
+    .unwrap();
+    let mut engine = Engine::with_config(config);
+    engine.set_invariant_check(InvariantCheck::Each);
+
+    let transactions = [
+        (TransactionType::Deposit, 1, Some(dec!(5))),
+        (TransactionType::Dispute, 1, None),
+    ];
+
+    for (tx_type, tx, amount) in transactions {
+        let trans = Transaction {
+            tx_type,
+            client: 1,
+            tx,
+            amount,
+        };
+        assert!(engine.process_transaction(trans).is_ok());
+    }
+    assert!(engine.clients[&1].locked);
+    assert!(engine.check_invariants().is_empty());
+
+    // open dispute without held amount
+    engine.clients.insert(
+        2,
+        Client {
+            transactions: HashMap::from([(
+                9,
+                StoredTransaction {
+                    tx_type: TransactionType::Deposit,
+                    amount: dec!(1),
+                    dispute_state: DisputeState::Open,
+                },
+            )]),
+            ..Default::default()
+        },
+    );
+
+    let deposit = Transaction {
+        tx_type: TransactionType::Deposit,
+        client: 2,
+        tx: 3,
+        amount: Some(dec!(1)),
+    };
+    assert_eq!(
+        engine.process_transaction(deposit).unwrap_err().to_string(),
+        "Invariant violated: after transaction with id 3: \
+         client 2: held amount 0 differs from 1 of open deposit disputes [tx 9: 1]"
+    );
+}
+
+#[test]
+fn test_client_balances() {
+    let config: EngineConfig = toml::from_str(
+        r#"
+        [fees]
+        withdrawal_flat = "0.1"
+        "#,
+    )
+    .unwrap();
+    let mut engine = Engine::with_config(config);
+
+    let transactions = [
+        (TransactionType::Deposit, 2, 1, Some(dec!(10))),
+        (TransactionType::Withdrawal, 2, 2, Some(dec!(3))),
+        (TransactionType::Dispute, 2, 1, None),
+        (TransactionType::Deposit, 1, 3, Some(dec!(5))),
+    ];
+
+    for (tx_type, client, tx, amount) in transactions {
+        let trans = Transaction {
+            tx_type,
+            client,
+            tx,
+            amount,
+        };
+        assert!(engine.process_transaction(trans).is_ok());
+    }
+
+    let balances = engine.client_balances();
+    assert_eq!(
+        balances,
+        vec![
+            ClientBalance {
+                client: 1,
+                available: dec!(5),
+                held: Decimal::ZERO,
+                total: dec!(5),
+                locked: false,
+                open_disputes: 0,
+                limit_breaches: None,
+                fees: Some(Decimal::ZERO),
+            },
+            ClientBalance {
+                client: 2,
+                available: dec!(-3.1),
+                held: dec!(10),
+                total: dec!(6.9),
+                locked: false,
+                open_disputes: 1,
+                limit_breaches: None,
+                fees: Some(dec!(0.1)),
+            },
+        ]
+    );
+    assert_eq!(
+        serde_json::to_string(&balances[1]).unwrap(),
+        r#"{"client":2,"available":"-3.1","held":"10","total":"6.9","locked":false,"open_disputes":1,"fees":"0.1"}"#
+    );
+}
+
 /// Step through all transactions and check resulting clients state
+#[test]
+fn test_multiple_inputs() {
+    let filenames = [
+        "data/input-merge1.csv".to_string(),
+        "data/input-merge2.csv".to_string(),
+    ];
+    let available_held = |engine: &Engine| {
+        let client = &engine.clients[&1];
+        (client.available, client.held)
+    };
+
+    // One after another, the withdrawal comes after both deposits
+    let mut engine = Engine::new();
+    let options = InputOptions {
+        dialect: CsvDialect {
+            columns: Some(ColumnMapping::parse("type,client,tx,amount").unwrap()),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    engine.read_and_process_input(&filenames, &options).unwrap();
+    assert_eq!(available_held(&engine), (dec!(-4.5), dec!(5.0)));
+
+    // Merged by sequence, the withdrawal comes before the second deposit and is rejected
+    let mut engine = Engine::new();
+    let options = InputOptions {
+        merge_by: Some(Column::Name("seq".to_string())),
+        ..Default::default()
+    };
+    engine.read_and_process_input(&filenames, &options).unwrap();
+    assert_eq!(available_held(&engine), (dec!(1.0), dec!(5.0)));
+
+    // Errors name the file
+    let mut engine = Engine::new();
+    let filenames = [
+        "data/input-flow1.csv".to_string(),
+        "data/missing.csv".to_string(),
+    ];
+    let err = engine
+        .read_and_process_input(&filenames, &InputOptions::default())
+        .unwrap_err();
+    assert!(
+        matches!(&err, EngineError::InFile { filename, .. } if filename == "data/missing.csv"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn test_atomic_inputs() {
+    let invalid = temp_path("atomic.csv");
+    std::fs::write(
+        &invalid,
+        "type,client,tx,amount\ndeposit,1,100,3.0\ndeposit,7,101,1.0\ndeposit,1,102,x\n",
+    )
+    .unwrap();
+    let filenames = [
+        "data/input-flow1.csv".to_string(),
+        invalid.display().to_string(),
+    ];
+
+    let mut expected = Engine::new();
+    expected
+        .read_and_process_input(&filenames[..1], &InputOptions::default())
+        .unwrap();
+
+    let mut engine = Engine::new();
+    let rolled_back = engine
+        .read_and_process_atomically(&filenames, &InputOptions::default())
+        .unwrap();
+
+    assert_eq!(rolled_back.len(), 1);
+    assert!(
+        matches!(&rolled_back[0], EngineError::InFile { source, .. } if matches!(**source, EngineError::BadDecimal { line: 4, .. })),
+        "{}",
+        rolled_back[0]
+    );
+    assert_eq!(engine.client_balances(), expected.client_balances());
+    engine.verify_ledger().unwrap();
+    std::fs::remove_file(invalid).unwrap();
+}
+
 macro_rules! test_transactions {
     ( $t_a_c:ident ) => {
         let mut engine = Engine::new();
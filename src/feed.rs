@@ -0,0 +1,117 @@
+use std::{
+    io::{self, Write},
+    sync::mpsc,
+};
+
+use serde::Serialize;
+
+use crate::types::{Amount, ClientId, TransactionId, TransactionType};
+
+/// Client account after transaction changed its `available`, `held` or `locked`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct AccountChanged {
+    /// Number of change starting from 1 without gaps, missing number means lost record
+    pub(crate) sequence: u64,
+    pub(crate) client: ClientId,
+    /// Transaction which caused the change
+    pub(crate) tx: TransactionId,
+    #[serde(rename = "type")]
+    pub(crate) tx_type: TransactionType,
+    pub(crate) available: Amount,
+    pub(crate) held: Amount,
+    pub(crate) total: Amount,
+    pub(crate) locked: bool,
+    /// Change made by rollback of `tx` and transactions after it, account is as before `tx`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) rolled_back: bool,
+}
+
+/// Receiver of account changes, sink which fails is unsubscribed.
+pub(crate) trait ChangeSink {
+    fn notify(&mut self, change: &AccountChanged) -> io::Result<()>;
+}
+
+/// In-process subscriber, unsubscribed when receiver is dropped.
+impl ChangeSink for mpsc::Sender<AccountChanged> {
+    fn notify(&mut self, change: &AccountChanged) -> io::Result<()> {
+        self.send(change.clone())
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "receiver is dropped"))
+    }
+}
+
+/// One JSON object per line, flushed after each change.
+pub(crate) struct JsonLines<W>(pub(crate) W);
+
+impl<W: Write> ChangeSink for JsonLines<W> {
+    fn notify(&mut self, change: &AccountChanged) -> io::Result<()> {
+        serde_json::to_writer(&mut self.0, change)?;
+        self.0.write_all(b"\n")?;
+        self.0.flush()
+    }
+}
+
+/// Subscribers of account changes with sequence number of the last change.
+#[derive(Default)]
+pub(crate) struct ChangeFeed {
+    pub(crate) sequence: u64,
+    sinks: Vec<Box<dyn ChangeSink>>,
+    /// Changes of open batch, sent only when it is committed
+    held: Option<Vec<AccountChanged>>,
+}
+
+impl ChangeFeed {
+    pub(crate) fn subscribe(&mut self, sink: Box<dyn ChangeSink>) {
+        self.sinks.push(sink);
+    }
+
+    /// Number `change` and send it to all sinks, or hold it until [`ChangeFeed::release`].
+    pub(crate) fn publish(&mut self, mut change: AccountChanged) {
+        self.sequence += 1;
+        change.sequence = self.sequence;
+
+        match &mut self.held {
+            Some(held) => held.push(change),
+            None => self.send(&change),
+        }
+    }
+
+    pub(crate) fn hold(&mut self) {
+        self.held = Some(Vec::new());
+    }
+
+    /// Send held changes and stop holding.
+    pub(crate) fn release(&mut self) {
+        for change in self.held.take().unwrap_or_default() {
+            self.send(&change);
+        }
+    }
+
+    /// Drop held changes and stop holding, sequence is reverted by [`ChangeFeed::retract`].
+    pub(crate) fn discard(&mut self) {
+        self.held = None;
+    }
+
+    /// Drop held changes numbered after `sequence` and continue numbering from it.
+    ///
+    /// Changes after `sequence` must not be sent yet, so that no number is reused for different change.
+    pub(crate) fn retract(&mut self, sequence: u64) {
+        if let Some(held) = &mut self.held {
+            held.retain(|change| change.sequence <= sequence);
+        }
+        self.sequence = sequence;
+    }
+
+    fn send(&mut self, change: &AccountChanged) {
+        self.sinks.retain_mut(|sink| match sink.notify(change) {
+            Ok(()) => true,
+            Err(err) => {
+                eprintln!("Change feed sink removed: {}", err);
+                false
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+#[path = "feed.test.rs"]
+mod tests;
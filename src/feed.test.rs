@@ -0,0 +1,113 @@
+use std::sync::mpsc;
+
+use rust_decimal_macros::dec;
+
+use crate::{
+    engine::Engine,
+    feed::{AccountChanged, ChangeSink, JsonLines},
+    testing::process,
+    types::TransactionType,
+};
+
+#[test]
+fn test_channel_receives_changes() {
+    let mut engine = Engine::new();
+    engine.allow_admin_transactions();
+    let (sender, receiver) = mpsc::channel();
+    engine.subscribe(Box::new(sender));
+
+    process(
+        &mut engine,
+        &[
+            "deposit, 1, 1, 10.0",
+            // Rejected, nothing changes
+            "withdrawal, 1, 2, 20.0",
+            "limit, 1, 3, 5.0",
+            "dispute, 1, 1,",
+            "chargeback, 1, 1,",
+        ],
+    );
+
+    let changes: Vec<_> = receiver.try_iter().collect();
+    assert_eq!(
+        changes,
+        vec![
+            AccountChanged {
+                sequence: 1,
+                client: 1,
+                tx: 1,
+                tx_type: TransactionType::Deposit,
+                available: dec!(10.0),
+                held: dec!(0),
+                total: dec!(10.0),
+                locked: false,
+                rolled_back: false,
+            },
+            AccountChanged {
+                sequence: 2,
+                client: 1,
+                tx: 1,
+                tx_type: TransactionType::Dispute,
+                available: dec!(0),
+                held: dec!(10.0),
+                total: dec!(10.0),
+                locked: false,
+                rolled_back: false,
+            },
+            AccountChanged {
+                sequence: 3,
+                client: 1,
+                tx: 1,
+                tx_type: TransactionType::Chargeback,
+                available: dec!(0),
+                held: dec!(0),
+                total: dec!(0),
+                locked: true,
+                rolled_back: false,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_dropped_receiver_is_unsubscribed() {
+    let mut engine = Engine::new();
+    let (sender, receiver) = mpsc::channel();
+    engine.subscribe(Box::new(sender));
+    let (sender, other) = mpsc::channel();
+    engine.subscribe(Box::new(sender));
+
+    process(&mut engine, &["deposit, 1, 1, 1.0"]);
+    drop(receiver);
+    process(&mut engine, &["deposit, 2, 2, 1.0"]);
+
+    let sequences: Vec<_> = other.try_iter().map(|change| change.sequence).collect();
+    assert_eq!(sequences, vec![1, 2]);
+}
+
+#[test]
+fn test_json_lines() {
+    let mut engine = Engine::new();
+    let (sender, receiver) = mpsc::channel();
+    engine.subscribe(Box::new(sender));
+    process(
+        &mut engine,
+        &["deposit, 1, 1, 2.5", "withdrawal, 1, 2, 1.0"],
+    );
+
+    let mut output = Vec::new();
+    let mut sink = JsonLines(&mut output);
+    for change in receiver.try_iter() {
+        sink.notify(&change).unwrap();
+    }
+
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        concat!(
+            r#"{"sequence":1,"client":1,"tx":1,"type":"deposit","available":"2.5","held":"0","total":"2.5","locked":false}"#,
+            "\n",
+            r#"{"sequence":2,"client":1,"tx":2,"type":"withdrawal","available":"1.5","held":"0","total":"1.5","locked":false}"#,
+            "\n",
+        )
+    );
+}
@@ -0,0 +1,63 @@
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use crate::types::Amount;
+
+/// Fees charged to client and credited to house account. Unset fees are not charged.
+#[derive(Debug, Default, Clone, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct FeeSchedule {
+    /// Flat fee of each withdrawal
+    #[serde(default, with = "rust_decimal::serde::str_option")]
+    pub(crate) withdrawal_flat: Option<Amount>,
+    /// Fee of deposit in percent of deposited amount
+    #[serde(default, with = "rust_decimal::serde::str_option")]
+    pub(crate) deposit_percent: Option<Amount>,
+    /// Minimum of percentage deposit fee
+    #[serde(default, with = "rust_decimal::serde::str_option")]
+    pub(crate) deposit_min: Option<Amount>,
+    /// Maximum of percentage deposit fee
+    #[serde(default, with = "rust_decimal::serde::str_option")]
+    pub(crate) deposit_cap: Option<Amount>,
+    /// Return fee of charged back transaction to client, disputes alone never reverse fees
+    pub(crate) reverse_on_chargeback: Option<bool>,
+}
+
+impl FeeSchedule {
+    /// Fields set in `self` take precedence over `defaults`.
+    pub(crate) fn or(&self, defaults: &Self) -> Self {
+        Self {
+            withdrawal_flat: self.withdrawal_flat.or(defaults.withdrawal_flat),
+            deposit_percent: self.deposit_percent.or(defaults.deposit_percent),
+            deposit_min: self.deposit_min.or(defaults.deposit_min),
+            deposit_cap: self.deposit_cap.or(defaults.deposit_cap),
+            reverse_on_chargeback: self
+                .reverse_on_chargeback
+                .or(defaults.reverse_on_chargeback),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.withdrawal_flat.is_none() && self.deposit_percent.is_none()
+    }
+
+    pub(crate) fn deposit_fee(&self, amount: Amount) -> Amount {
+        let Some(percent) = self.deposit_percent else {
+            return Decimal::ZERO;
+        };
+
+        let mut fee = amount * percent / Decimal::ONE_HUNDRED;
+        if let Some(min) = self.deposit_min {
+            fee = fee.max(min);
+        }
+        if let Some(cap) = self.deposit_cap {
+            fee = fee.min(cap);
+        }
+        // Minimum fee of small deposit takes at most the whole deposit
+        fee.min(amount)
+    }
+
+    pub(crate) fn withdrawal_fee(&self) -> Amount {
+        self.withdrawal_flat.unwrap_or(Decimal::ZERO)
+    }
+}
@@ -0,0 +1,241 @@
+use std::{
+    collections::HashMap, future::Future, net::TcpListener, str::FromStr, sync::mpsc, thread,
+};
+
+use rust_decimal::Decimal;
+use tokio::sync::{mpsc::UnboundedSender, oneshot};
+use tokio_stream::wrappers::{TcpListenerStream, UnboundedReceiverStream};
+use tonic::{codec::Streaming, transport::Server, Request, Response, Status};
+
+use crate::{
+    engine::Engine,
+    types::{ClientId, EngineError, Transaction, TransactionOutcome, TransactionType},
+};
+
+/// Messages and service of `proto/stte.proto` generated by `build.rs`.
+pub(crate) mod proto {
+    tonic::include_proto!("stte");
+}
+
+type Watcher = UnboundedSender<Result<proto::ClientBalance, Status>>;
+
+/// Request of service to engine thread.
+enum Command {
+    Submit(proto::Transaction, oneshot::Sender<proto::SubmitResult>),
+    Watch(ClientId, Watcher),
+}
+
+/// Serve engine over gRPC until the process is stopped, see `proto/stte.proto`.
+pub(crate) fn serve(engine: &mut Engine, address: &str) -> Result<(), EngineError> {
+    let listener = TcpListener::bind(address)?;
+    eprintln!("Listening on {}", listener.local_addr()?);
+    run(engine, listener, std::future::pending())
+}
+
+/// Serve connections of `listener` until `shutdown` completes.
+///
+/// Server runs on its own runtime thread, engine stays in the calling thread
+/// and applies transactions one by one in order of arrival.
+pub(crate) fn run(
+    engine: &mut Engine,
+    listener: TcpListener,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> Result<(), EngineError> {
+    listener.set_nonblocking(true)?;
+    let (commands, receiver) = mpsc::channel();
+    let service = EngineService { commands };
+
+    let server = thread::spawn(move || -> Result<(), EngineError> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()?;
+        runtime.block_on(async move {
+            let incoming = TcpListenerStream::new(tokio::net::TcpListener::from_std(listener)?);
+            Server::builder()
+                .add_service(proto::engine_server::EngineServer::new(service))
+                .serve_with_incoming_shutdown(incoming, shutdown)
+                .await?;
+            Ok(())
+        })
+    });
+
+    // Ends when the server is stopped and all senders are dropped
+    let mut watchers = HashMap::new();
+    for command in receiver {
+        handle(engine, &mut watchers, command);
+    }
+
+    server.join().expect("gRPC server thread panicked")
+}
+
+fn handle(engine: &mut Engine, watchers: &mut HashMap<ClientId, Vec<Watcher>>, command: Command) {
+    match command {
+        Command::Submit(transaction, reply) => {
+            let result = submit(engine, transaction);
+            if result.outcome == proto::Outcome::Applied as i32 {
+                let client = result.client as ClientId;
+                if let (Some(senders), Some(balance)) =
+                    (watchers.get_mut(&client), engine.client_balance(client))
+                {
+                    let balance = client_balance(balance);
+                    // Drop watchers of closed streams
+                    senders.retain(|sender| sender.send(Ok(balance.clone())).is_ok());
+                }
+            }
+            // Client may be gone already
+            let _ = reply.send(result);
+        }
+        Command::Watch(client, sender) => {
+            if let Some(balance) = engine.client_balance(client) {
+                if sender.send(Ok(client_balance(balance))).is_err() {
+                    return;
+                }
+            }
+            watchers.entry(client).or_default().push(sender);
+        }
+    }
+}
+
+fn submit(engine: &mut Engine, message: proto::Transaction) -> proto::SubmitResult {
+    let mut result = proto::SubmitResult {
+        client: message.client,
+        tx: message.tx,
+        ..Default::default()
+    };
+
+    let processed =
+        transaction(message).and_then(|transaction| engine.process_transaction(transaction));
+    match processed {
+        Ok(TransactionOutcome::Applied) => result.outcome = proto::Outcome::Applied as i32,
+        Ok(TransactionOutcome::Rejected(reason)) => {
+            result.outcome = proto::Outcome::Rejected as i32;
+            result.reason = reason.name();
+        }
+        Err(err) => {
+            result.outcome = proto::Outcome::Error as i32;
+            result.reason = err.to_string();
+        }
+    }
+
+    result
+}
+
+fn transaction(message: proto::Transaction) -> Result<Transaction, EngineError> {
+    let tx_type = match proto::TransactionType::try_from(message.r#type) {
+        Ok(proto::TransactionType::Deposit) => TransactionType::Deposit,
+        Ok(proto::TransactionType::Withdrawal) => TransactionType::Withdrawal,
+        Ok(proto::TransactionType::Dispute) => TransactionType::Dispute,
+        Ok(proto::TransactionType::Resolve) => TransactionType::Resolve,
+        Ok(proto::TransactionType::Chargeback) => TransactionType::Chargeback,
+        Err(_) => {
+            return Err(EngineError::InvalidMessage(format!(
+                "unknown transaction type {}",
+                message.r#type
+            )))
+        }
+    };
+    let client = ClientId::try_from(message.client).map_err(|_| {
+        EngineError::InvalidMessage(format!("client id {} is out of range", message.client))
+    })?;
+    let amount = message
+        .amount
+        .map(|amount| {
+            Decimal::from_str(amount.trim()).map_err(|_| {
+                EngineError::InvalidMessage(format!("invalid decimal amount {:?}", amount))
+            })
+        })
+        .transpose()?;
+
+    Ok(Transaction {
+        tx_type,
+        client,
+        tx: message.tx,
+        amount,
+    })
+}
+
+fn client_balance(balance: crate::output::ClientBalance) -> proto::ClientBalance {
+    proto::ClientBalance {
+        client: balance.client.into(),
+        available: balance.available.to_string(),
+        held: balance.held.to_string(),
+        total: balance.total.to_string(),
+        locked: balance.locked,
+        open_disputes: balance.open_disputes as u64,
+    }
+}
+
+/// Implementation of `stte.Engine` service forwarding requests to engine thread.
+#[derive(Clone)]
+struct EngineService {
+    commands: mpsc::Sender<Command>,
+}
+
+impl EngineService {
+    async fn submit(&self, transaction: proto::Transaction) -> Result<proto::SubmitResult, Status> {
+        let (reply, result) = oneshot::channel();
+        self.commands
+            .send(Command::Submit(transaction, reply))
+            .map_err(|_| Status::unavailable("engine is stopped"))?;
+        result
+            .await
+            .map_err(|_| Status::unavailable("engine is stopped"))
+    }
+
+    async fn submit_stream(
+        &self,
+        mut transactions: Streaming<proto::Transaction>,
+    ) -> Result<proto::BatchResult, Status> {
+        let mut results = Vec::new();
+        while let Some(transaction) = transactions.message().await? {
+            results.push(self.submit(transaction).await?);
+        }
+        Ok(proto::BatchResult { results })
+    }
+
+    async fn watch(
+        &self,
+        request: proto::WatchRequest,
+    ) -> Result<UnboundedReceiverStream<Result<proto::ClientBalance, Status>>, Status> {
+        let client = ClientId::try_from(request.client).map_err(|_| {
+            Status::invalid_argument(format!("client id {} is out of range", request.client))
+        })?;
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        self.commands
+            .send(Command::Watch(client, sender))
+            .map_err(|_| Status::unavailable("engine is stopped"))?;
+        Ok(UnboundedReceiverStream::new(receiver))
+    }
+}
+
+#[tonic::async_trait]
+impl proto::engine_server::Engine for EngineService {
+    async fn submit_transaction(
+        &self,
+        request: Request<proto::Transaction>,
+    ) -> Result<Response<proto::SubmitResult>, Status> {
+        self.submit(request.into_inner()).await.map(Response::new)
+    }
+
+    async fn submit_batch(
+        &self,
+        request: Request<Streaming<proto::Transaction>>,
+    ) -> Result<Response<proto::BatchResult>, Status> {
+        self.submit_stream(request.into_inner())
+            .await
+            .map(Response::new)
+    }
+
+    type WatchClientStream = UnboundedReceiverStream<Result<proto::ClientBalance, Status>>;
+
+    async fn watch_client(
+        &self,
+        request: Request<proto::WatchRequest>,
+    ) -> Result<Response<Self::WatchClientStream>, Status> {
+        self.watch(request.into_inner()).await.map(Response::new)
+    }
+}
+
+#[cfg(test)]
+#[path = "grpc.test.rs"]
+mod tests;
@@ -0,0 +1,138 @@
+use std::{net::TcpListener, thread};
+
+use tokio::sync::oneshot;
+use tonic::transport::Endpoint;
+
+use crate::{
+    engine::Engine,
+    grpc::{proto, proto::engine_client::EngineClient, run},
+};
+
+fn transaction(
+    tx_type: proto::TransactionType,
+    client: u32,
+    tx: u32,
+    amount: Option<&str>,
+) -> proto::Transaction {
+    proto::Transaction {
+        r#type: tx_type as i32,
+        client,
+        tx,
+        amount: amount.map(str::to_string),
+    }
+}
+
+fn result(client: u32, tx: u32, outcome: proto::Outcome, reason: &str) -> proto::SubmitResult {
+    proto::SubmitResult {
+        client,
+        tx,
+        outcome: outcome as i32,
+        reason: reason.to_string(),
+    }
+}
+
+fn balance(available: &str, held: &str, total: &str) -> proto::ClientBalance {
+    proto::ClientBalance {
+        client: 1,
+        available: available.to_string(),
+        held: held.to_string(),
+        total: total.to_string(),
+        locked: false,
+        open_disputes: 0,
+    }
+}
+
+#[test]
+fn test_service() {
+    use proto::TransactionType::*;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let (stop, stopped) = oneshot::channel::<()>();
+
+    let client = thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let results = runtime.block_on(async move {
+            let channel = Endpoint::from_shared(format!("http://{}", address))
+                .unwrap()
+                .connect()
+                .await
+                .unwrap();
+            let mut client = EngineClient::new(channel);
+
+            let deposit = client
+                .submit_transaction(transaction(Deposit, 1, 1, Some("2.0")))
+                .await
+                .unwrap()
+                .into_inner();
+            let unknown_type = client
+                .submit_transaction(proto::Transaction {
+                    r#type: 9,
+                    ..transaction(Deposit, 1, 5, Some("1.0"))
+                })
+                .await
+                .unwrap()
+                .into_inner();
+
+            let mut watch = client
+                .watch_client(proto::WatchRequest { client: 1 })
+                .await
+                .unwrap()
+                .into_inner();
+            let current = watch.message().await.unwrap().unwrap();
+
+            let batch = vec![
+                transaction(Withdrawal, 1, 2, Some("0.5")),
+                transaction(Withdrawal, 1, 3, Some("100")),
+                transaction(Deposit, 70000, 4, Some("1.0")),
+            ];
+            let batch = client
+                .submit_batch(tokio_stream::iter(batch))
+                .await
+                .unwrap()
+                .into_inner();
+            let changed = watch.message().await.unwrap().unwrap();
+
+            (deposit, unknown_type, current, batch, changed)
+        });
+        drop(runtime);
+        stop.send(()).unwrap();
+        results
+    });
+
+    let mut engine = Engine::new();
+    run(&mut engine, listener, async {
+        let _ = stopped.await;
+    })
+    .unwrap();
+    let (deposit, unknown_type, current, batch, changed) = client.join().unwrap();
+
+    assert_eq!(deposit, result(1, 1, proto::Outcome::Applied, ""));
+    assert_eq!(
+        unknown_type,
+        result(
+            1,
+            5,
+            proto::Outcome::Error,
+            "Invalid gRPC message: unknown transaction type 9"
+        )
+    );
+    assert_eq!(current, balance("2.0", "0", "2.0"));
+    assert_eq!(
+        batch.results,
+        vec![
+            result(1, 2, proto::Outcome::Applied, ""),
+            result(1, 3, proto::Outcome::Rejected, "insufficient_funds"),
+            result(
+                70000,
+                4,
+                proto::Outcome::Error,
+                "Invalid gRPC message: client id 70000 is out of range"
+            ),
+        ]
+    );
+    assert_eq!(changed, balance("1.5", "0", "1.5"));
+}
@@ -0,0 +1,294 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    path::Path,
+};
+
+use flate2::bufread::MultiGzDecoder;
+use serde_json::{value::RawValue, Value};
+
+#[cfg(feature = "arrow")]
+use crate::columnar;
+use crate::{
+    dialect::{self, Column, CsvDialect},
+    merge,
+    types::{EngineError, Transaction},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum InputFormat {
+    Csv,
+    /// JSON array of transactions
+    Json,
+    /// One JSON transaction object per line
+    JsonLines,
+    /// Arrow IPC file with columns `type`, `client`, `tx` and `amount`
+    #[cfg(feature = "arrow")]
+    ArrowIpc,
+    /// Parquet file with columns `type`, `client`, `tx` and `amount`
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+impl InputFormat {
+    /// Detect format from file extension ignoring compression extension, CSV is the default.
+    pub(crate) fn from_path(filename: &str) -> Self {
+        let path = match Compression::from_path(filename) {
+            Compression::None => Path::new(filename),
+            _ => Path::new(Path::new(filename).file_stem().unwrap_or_default()),
+        };
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => InputFormat::Json,
+            Some("jsonl" | "ndjson") => InputFormat::JsonLines,
+            #[cfg(feature = "arrow")]
+            Some("arrow" | "ipc") => InputFormat::ArrowIpc,
+            #[cfg(feature = "parquet")]
+            Some("parquet") => InputFormat::Parquet,
+            _ => InputFormat::Csv,
+        }
+    }
+
+    pub(crate) fn parse(format: &str) -> Option<Self> {
+        match format {
+            "csv" => Some(InputFormat::Csv),
+            "json" => Some(InputFormat::Json),
+            "jsonl" => Some(InputFormat::JsonLines),
+            #[cfg(feature = "arrow")]
+            "arrow" => Some(InputFormat::ArrowIpc),
+            #[cfg(feature = "parquet")]
+            "parquet" => Some(InputFormat::Parquet),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    pub(crate) fn from_path(filename: &str) -> Self {
+        match Path::new(filename)
+            .extension()
+            .and_then(|extension| extension.to_str())
+        {
+            Some("gz") => Compression::Gzip,
+            Some("zst") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+
+    pub(crate) fn from_magic(header: &[u8]) -> Self {
+        match header {
+            [0x1f, 0x8b, ..] => Compression::Gzip,
+            [0x28, 0xb5, 0x2f, 0xfd, ..] => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+}
+
+/// How to read input file.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct InputOptions {
+    /// Detected from file extension when not set
+    pub(crate) format: Option<InputFormat>,
+    /// Used only for CSV input
+    pub(crate) dialect: CsvDialect,
+    /// Merge multiple CSV inputs ordered by this column instead of reading them one after another
+    pub(crate) merge_by: Option<Column>,
+}
+
+pub(crate) type Transactions = Box<dyn Iterator<Item = Result<Transaction, EngineError>>>;
+/// Transactions with value of the column inputs are merged by
+pub(crate) type KeyedTransactions =
+    Box<dyn Iterator<Item = Result<(String, Transaction), EngineError>>>;
+/// Transactions of multiple inputs with index of input they come from
+pub(crate) type TaggedTransactions =
+    Box<dyn Iterator<Item = (usize, Result<Transaction, EngineError>)>>;
+
+pub(crate) fn read_transactions(
+    filename: &str,
+    options: &InputOptions,
+) -> Result<Transactions, EngineError> {
+    let format = options
+        .format
+        .unwrap_or_else(|| InputFormat::from_path(filename));
+
+    #[cfg(feature = "arrow")]
+    if format == InputFormat::ArrowIpc {
+        return columnar::read_arrow_ipc(open_seekable(filename)?);
+    }
+    #[cfg(feature = "parquet")]
+    if format == InputFormat::Parquet {
+        return columnar::read_parquet(open_seekable(filename)?);
+    }
+
+    read_transactions_from(open(filename)?, format, &options.dialect)
+}
+
+/// Open file decompressing gzip or zstd detected by extension or magic bytes while streaming.
+fn open(filename: &str) -> Result<Box<dyn Read>, EngineError> {
+    let mut reader = BufReader::new(File::open(filename)?);
+    let compression = match Compression::from_path(filename) {
+        Compression::None => Compression::from_magic(reader.fill_buf()?),
+        compression => compression,
+    };
+
+    Ok(match compression {
+        Compression::None => Box::new(reader),
+        Compression::Gzip => Box::new(BufReader::new(MultiGzDecoder::new(reader))),
+        Compression::Zstd => Box::new(BufReader::new(zstd::Decoder::with_buffer(reader)?)),
+    })
+}
+
+/// Read inputs one after another or merged by [`InputOptions::merge_by`] column.
+pub(crate) fn read_inputs(filenames: &[String], options: &InputOptions) -> TaggedTransactions {
+    let Some(key) = &options.merge_by else {
+        // Files are opened only when reached
+        let options = options.clone();
+        let filenames: Vec<String> = filenames.to_vec();
+        return Box::new(filenames.into_iter().enumerate().flat_map(
+            move |(index, filename)| -> TaggedTransactions {
+                match read_transactions(&filename, &options) {
+                    Ok(transactions) => Box::new(transactions.map(move |result| (index, result))),
+                    Err(err) => Box::new(std::iter::once((index, Err(err)))),
+                }
+            },
+        ));
+    };
+
+    merge::merge(
+        filenames
+            .iter()
+            .map(|filename| read_keyed(filename, options, key))
+            .collect(),
+    )
+}
+
+fn read_keyed(
+    filename: &str,
+    options: &InputOptions,
+    key: &Column,
+) -> Result<KeyedTransactions, EngineError> {
+    let format = options
+        .format
+        .unwrap_or_else(|| InputFormat::from_path(filename));
+    if format != InputFormat::Csv {
+        return Err(EngineError::InvalidMerge(
+            "only CSV inputs can be merged".to_string(),
+        ));
+    }
+
+    dialect::read_csv_keyed(open(filename)?, &options.dialect, key)
+}
+
+/// Deserialize transactions from reader in given format.
+///
+/// Note: CSV and JSON Lines are streamed, JSON array is read whole into memory.
+/// Each JSON line and array element is parsed separately, so an invalid one does not hide the following.
+/// Columnar formats need seekable file, see [`read_transactions`].
+pub(crate) fn read_transactions_from(
+    reader: Box<dyn Read>,
+    format: InputFormat,
+    dialect: &CsvDialect,
+) -> Result<Transactions, EngineError> {
+    Ok(match format {
+        InputFormat::Csv => dialect::read_csv(reader, dialect)?,
+        InputFormat::Json => Box::new(read_json_array(reader)?.into_iter()),
+        InputFormat::JsonLines => Box::new(
+            BufReader::new(reader)
+                .lines()
+                .zip(1..)
+                .filter(|(text, _)| !matches!(text, Ok(text) if text.trim().is_empty()))
+                .map(|(text, line)| {
+                    let error = |err: serde_json::Error| EngineError::InvalidJsonLine {
+                        line,
+                        message: match err.line() {
+                            0 => json_message(&err),
+                            _ => format!("{} at column {}", json_message(&err), err.column()),
+                        },
+                    };
+                    parse_json(&serde_json::from_str(&text?).map_err(error)?, line)
+                }),
+        ),
+        #[cfg(feature = "arrow")]
+        InputFormat::ArrowIpc => return Err(not_seekable()),
+        #[cfg(feature = "parquet")]
+        InputFormat::Parquet => return Err(not_seekable()),
+    })
+}
+
+/// Transactions of JSON array, elements are parsed separately and their errors located by line.
+fn read_json_array(
+    mut reader: Box<dyn Read>,
+) -> Result<Vec<Result<Transaction, EngineError>>, EngineError> {
+    let mut content = String::new();
+    reader.read_to_string(&mut content)?;
+    let elements: Vec<&RawValue> = serde_json::from_str(&content)?;
+
+    Ok(elements
+        .into_iter()
+        .zip(1..)
+        .map(|(element, number)| {
+            let offset = element.get().as_ptr() as usize - content.as_ptr() as usize;
+            let line = content[..offset].matches('\n').count() as u64 + 1;
+            match serde_json::from_str(element.get())? {
+                Value::Object(object) => dialect::parse_object(&object, line),
+                value => Err(EngineError::InvalidJsonElement {
+                    element: number,
+                    line,
+                    message: not_object(&value),
+                }),
+            }
+        })
+        .collect())
+}
+
+/// Transaction of JSON object in `line`, see [`dialect::parse_object`].
+pub(crate) fn parse_json(value: &Value, line: u64) -> Result<Transaction, EngineError> {
+    match value {
+        Value::Object(object) => dialect::parse_object(object, line),
+        value => Err(EngineError::InvalidJsonLine {
+            line,
+            message: not_object(value),
+        }),
+    }
+}
+
+fn not_object(value: &Value) -> String {
+    format!("expected transaction object, got {value}")
+}
+
+/// Error of JSON record without location within the record, which is reported by caller.
+fn json_message(err: &serde_json::Error) -> String {
+    let message = err.to_string();
+    let location = format!(" at line {} column {}", err.line(), err.column());
+    match message.strip_suffix(&location) {
+        Some(message) if err.line() > 0 => message.to_string(),
+        _ => message,
+    }
+}
+
+/// Columnar formats are read by seeking in uncompressed file.
+#[cfg(feature = "arrow")]
+fn open_seekable(filename: &str) -> Result<File, EngineError> {
+    if Compression::from_path(filename) != Compression::None {
+        return Err(not_seekable());
+    }
+    Ok(File::open(filename)?)
+}
+
+#[cfg(feature = "arrow")]
+fn not_seekable() -> EngineError {
+    EngineError::Io(std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        "columnar input must be read from uncompressed file",
+    ))
+}
+
+#[cfg(test)]
+#[path = "input.test.rs"]
+mod tests;
@@ -0,0 +1,242 @@
+use rust_decimal_macros::dec;
+
+use crate::{
+    cli::{exit_code, EXIT_PARSE},
+    dialect::CsvDialect,
+    input::{read_transactions, read_transactions_from, Compression, InputFormat, InputOptions},
+    types::{EngineError, Transaction, TransactionType},
+};
+
+fn expected_transactions() -> Vec<Transaction> {
+    vec![
+        Transaction {
+            tx_type: TransactionType::Deposit,
+            client: 1,
+            tx: 1,
+            amount: Some(dec!(1.5)),
+        },
+        Transaction {
+            tx_type: TransactionType::Dispute,
+            client: 1,
+            tx: 1,
+            amount: None,
+        },
+    ]
+}
+
+fn read(input: &'static str, format: InputFormat) -> Vec<Transaction> {
+    read_transactions_from(Box::new(input.as_bytes()), format, &CsvDialect::default())
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+#[test]
+fn test_format_from_path() {
+    assert_eq!(InputFormat::from_path("data/input.csv"), InputFormat::Csv);
+    assert_eq!(InputFormat::from_path("data/input"), InputFormat::Csv);
+    assert_eq!(InputFormat::from_path("input.json"), InputFormat::Json);
+    assert_eq!(
+        InputFormat::from_path("input.jsonl"),
+        InputFormat::JsonLines
+    );
+    assert_eq!(
+        InputFormat::from_path("input.ndjson"),
+        InputFormat::JsonLines
+    );
+}
+
+#[test]
+fn test_read_csv() {
+    let input = "type, client, tx, amount\ndeposit, 1, 1, 1.5\ndispute, 1, 1\n";
+    assert_eq!(read(input, InputFormat::Csv), expected_transactions());
+}
+
+#[test]
+fn test_read_json() {
+    let input = r#"[
+        {"type": "deposit", "client": 1, "tx": 1, "amount": "1.5"},
+        {"type": "dispute", "client": 1, "tx": 1, "amount": null}
+    ]"#;
+    assert_eq!(read(input, InputFormat::Json), expected_transactions());
+}
+
+#[test]
+fn test_read_json_lines() {
+    let input = concat!(
+        r#"{"type": "deposit", "client": 1, "tx": 1, "amount": "1.5"}"#,
+        "\n",
+        r#"{"type": "dispute", "client": 1, "tx": 1}"#,
+        "\n",
+    );
+    assert_eq!(read(input, InputFormat::JsonLines), expected_transactions());
+}
+
+#[test]
+fn test_invalid_json_lines() {
+    let input = r#"{"type": "depositt", "client": 1, "tx": 1, "amount": "1.5"}"#;
+    let err = read_transactions_from(
+        Box::new(input.as_bytes()),
+        InputFormat::JsonLines,
+        &CsvDialect::default(),
+    )
+    .unwrap()
+    .next()
+    .unwrap()
+    .unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("Unknown transaction type \"depositt\" in line 1, column type"));
+}
+
+#[test]
+fn test_compression_detection() {
+    assert_eq!(
+        InputFormat::from_path("data/input.csv.gz"),
+        InputFormat::Csv
+    );
+    assert_eq!(
+        InputFormat::from_path("input.jsonl.zst"),
+        InputFormat::JsonLines
+    );
+    assert_eq!(Compression::from_path("input.csv.gz"), Compression::Gzip);
+    assert_eq!(Compression::from_path("input.zst"), Compression::Zstd);
+    assert_eq!(Compression::from_path("input.csv"), Compression::None);
+    assert_eq!(
+        Compression::from_magic(&[0x1f, 0x8b, 0x08]),
+        Compression::Gzip
+    );
+    assert_eq!(
+        Compression::from_magic(&[0x28, 0xb5, 0x2f, 0xfd]),
+        Compression::Zstd
+    );
+    assert_eq!(Compression::from_magic(b"type"), Compression::None);
+}
+
+#[test]
+fn test_read_compressed() {
+    let read_file = |filename| {
+        read_transactions(filename, &InputOptions::default())
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap()
+    };
+
+    let expected = read_file("data/input-flow1.csv");
+    assert_eq!(expected.len(), 13);
+    assert_eq!(read_file("data/input-flow1.csv.gz"), expected);
+    assert_eq!(read_file("data/input-flow1.csv.zst"), expected);
+    // Gzip detected by magic bytes
+    assert_eq!(read_file("data/input-flow1-gzip"), expected);
+}
+
+fn errors(input: &'static str, format: InputFormat) -> Vec<String> {
+    read_transactions_from(Box::new(input.as_bytes()), format, &CsvDialect::default())
+        .unwrap()
+        .filter_map(|result| result.err().map(|err| err.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_json_errors_are_located() {
+    let input = concat!(
+        r#"{"type": "deposit", "client": 1, "tx": 1, "amount": "1.5"}"#,
+        "\n\n",
+        r#"{"type": "deposit", "client": 1, "tx": 2, "amount": }"#,
+        "\n",
+        r#"{"type": "depositt", "client": 1, "tx": 3}"#,
+        "\n",
+    );
+    assert_eq!(
+        errors(input, InputFormat::JsonLines),
+        [
+            "Error parsing JSON input in line 3: expected value at column 53",
+            "Unknown transaction type \"depositt\" in line 4, column type",
+        ]
+    );
+
+    let input = r#"[
+        {"type": "deposit", "client": 1, "tx": 1, "amount": "1.5"},
+
+        {"type": "deposit", "client": 1, "tx": 2}, 3,
+        {"type": "depositt", "client": 1, "tx": 3}
+    ]"#;
+    assert_eq!(
+        errors(input, InputFormat::Json),
+        [
+            "Error parsing JSON input in element 3, line 4: expected transaction object, got 3",
+            "Unknown transaction type \"depositt\" in line 5, column type",
+        ]
+    );
+}
+
+/// Error of the single record in CSV with header and JSON Lines after blank line, both in line 2.
+fn csv_and_json_error(record: &str, object: &str) -> (EngineError, EngineError) {
+    let error = |input: String, format| {
+        read_transactions_from(
+            Box::new(std::io::Cursor::new(input)),
+            format,
+            &CsvDialect::default(),
+        )
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap_err()
+    };
+    (
+        error(
+            format!("type,client,tx,amount\n{record}\n"),
+            InputFormat::Csv,
+        ),
+        error(format!("\n{object}\n"), InputFormat::JsonLines),
+    )
+}
+
+#[test]
+fn test_json_errors_match_csv() {
+    for (record, object) in [
+        (
+            "depositt,1,1,1.0",
+            r#"{"type": "depositt", "client": 1, "tx": 1, "amount": "1.0"}"#,
+        ),
+        (
+            "deposit,one,1,1.0",
+            r#"{"type": "deposit", "client": "one", "tx": 1, "amount": "1.0"}"#,
+        ),
+        (
+            "deposit,70000,1,1.0",
+            r#"{"type": "deposit", "client": 70000, "tx": 1, "amount": "1.0"}"#,
+        ),
+        (
+            "deposit,1,-1,1.0",
+            r#"{"type": "deposit", "client": 1, "tx": -1, "amount": "1.0"}"#,
+        ),
+        (
+            "deposit,1,1,1.x",
+            r#"{"type": "deposit", "client": 1, "tx": 1, "amount": "1.x"}"#,
+        ),
+    ] {
+        let (csv, json) = csv_and_json_error(record, object);
+        assert_eq!(csv.to_string(), json.to_string());
+        assert_eq!(exit_code(&csv), EXIT_PARSE);
+    }
+
+    let (csv, json) = csv_and_json_error(
+        "deposit,1,1,1.0,extra",
+        r#"{"type": "deposit", "client": 1, "tx": 1, "amount": "1.0", "note": "extra"}"#,
+    );
+    assert!(matches!(csv, EngineError::UnknownColumn { line: 2, .. }));
+    assert!(matches!(
+        json,
+        EngineError::UnknownColumn { line: 2, ref column, ref value } if column == "note" && value == "extra"
+    ));
+    assert_eq!(exit_code(&json), EXIT_PARSE);
+
+    let (csv, json) = csv_and_json_error("deposit,1", r#"{"type": "deposit", "client": 1}"#);
+    assert!(
+        matches!(csv, EngineError::MissingColumn { line: 2, ref column, .. } if column == "tx")
+    );
+    assert!(
+        matches!(json, EngineError::MissingColumn { line: 2, ref column, .. } if column == "tx")
+    );
+}
@@ -0,0 +1,138 @@
+use std::fmt;
+
+use rust_decimal::Decimal;
+
+use crate::types::{Amount, Client, ClientId, DisputeState, TransactionId, TransactionType};
+
+/// When the engine checks invariants of clients.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) enum InvariantCheck {
+    #[default]
+    Off,
+    /// Check client touched by each transaction right after it is processed
+    Each,
+    /// Check all clients after whole input is processed
+    End,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum InvariantViolation {
+    NegativeHeld {
+        client: ClientId,
+        held: Amount,
+    },
+    /// `held` differs from sum of amounts of deposits with open dispute
+    HeldMismatch {
+        client: ClientId,
+        held: Amount,
+        open_disputes: Vec<(TransactionId, Amount)>,
+    },
+    /// Account is locked without chargeback or risk rule lock
+    LockedWithoutChargeback {
+        client: ClientId,
+    },
+    ChargebackNotLocked {
+        client: ClientId,
+        tx: TransactionId,
+    },
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvariantViolation::NegativeHeld { client, held } => {
+                write!(f, "client {}: negative held amount {}", client, held)
+            }
+            InvariantViolation::HeldMismatch {
+                client,
+                held,
+                open_disputes,
+            } => {
+                let total: Amount = open_disputes.iter().map(|(_, amount)| amount).sum();
+                write!(
+                    f,
+                    "client {}: held amount {} differs from {} of open deposit disputes [",
+                    client, held, total
+                )?;
+                for (i, (tx, amount)) in open_disputes.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "tx {}: {}", tx, amount)?;
+                }
+                write!(f, "]")
+            }
+            InvariantViolation::LockedWithoutChargeback { client } => {
+                write!(f, "client {}: locked without any chargeback", client)
+            }
+            InvariantViolation::ChargebackNotLocked { client, tx } => {
+                write!(
+                    f,
+                    "client {}: not locked after chargeback of tx {}",
+                    client, tx
+                )
+            }
+        }
+    }
+}
+
+/// Validate client amounts and lock against its stored transactions.
+pub(crate) fn check_client(
+    client_id: ClientId,
+    client: &Client,
+    locked_by_rule: bool,
+) -> Vec<InvariantViolation> {
+    let mut violations = Vec::new();
+
+    if client.held < Decimal::ZERO {
+        violations.push(InvariantViolation::NegativeHeld {
+            client: client_id,
+            held: client.held,
+        });
+    }
+
+    let mut open_disputes: Vec<_> = client
+        .transactions
+        .iter()
+        .filter(|(_, stored)| {
+            stored.tx_type == TransactionType::Deposit && stored.dispute_state == DisputeState::Open
+        })
+        .map(|(tx, stored)| (*tx, stored.amount))
+        .collect();
+    open_disputes.sort();
+
+    let open_total: Amount = open_disputes.iter().map(|(_, amount)| amount).sum();
+    if client.held != open_total {
+        violations.push(InvariantViolation::HeldMismatch {
+            client: client_id,
+            held: client.held,
+            open_disputes,
+        });
+    }
+
+    let mut chargebacks: Vec<_> = client
+        .transactions
+        .iter()
+        .filter(|(_, stored)| stored.dispute_state == DisputeState::Chargeback)
+        .map(|(tx, _)| *tx)
+        .collect();
+    chargebacks.sort();
+
+    if client.locked && chargebacks.is_empty() && !locked_by_rule {
+        violations.push(InvariantViolation::LockedWithoutChargeback { client: client_id });
+    }
+    if !client.locked {
+        violations.extend(chargebacks.into_iter().map(|tx| {
+            InvariantViolation::ChargebackNotLocked {
+                client: client_id,
+                tx,
+            }
+        }));
+    }
+
+    violations
+}
+
+#[cfg(test)]
+#[path = "invariants.test.rs"]
+mod tests;
@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+use crate::{
+    invariants::{check_client, InvariantViolation},
+    types::{Client, DisputeState, StoredTransaction, TransactionType},
+};
+
+#[test]
+fn test_valid_client() {
+    let client = Client {
+        available: dec!(1),
+        held: dec!(2.5),
+        locked: true,
+        transactions: HashMap::from([
+            (
+                1,
+                StoredTransaction {
+                    tx_type: TransactionType::Deposit,
+                    amount: dec!(2.5),
+                    dispute_state: DisputeState::Open,
+                },
+            ),
+            (
+                2,
+                StoredTransaction {
+                    tx_type: TransactionType::Withdrawal,
+                    amount: dec!(1.5),
+                    dispute_state: DisputeState::Chargeback,
+                },
+            ),
+            (
+                3,
+                StoredTransaction {
+                    tx_type: TransactionType::Withdrawal,
+                    amount: dec!(1.5),
+                    dispute_state: DisputeState::Open,
+                },
+            ),
+        ]),
+    };
+
+    assert_eq!(check_client(1, &client, false), vec![]);
+}
+
+#[test]
+fn test_held_violations() {
+    let client = Client {
+        available: Decimal::ZERO,
+        held: dec!(-1),
+        locked: false,
+        transactions: HashMap::from([
+            (
+                2,
+                StoredTransaction {
+                    tx_type: TransactionType::Deposit,
+                    amount: dec!(2),
+                    dispute_state: DisputeState::Open,
+                },
+            ),
+            (
+                1,
+                StoredTransaction {
+                    tx_type: TransactionType::Deposit,
+                    amount: dec!(1),
+                    dispute_state: DisputeState::Open,
+                },
+            ),
+        ]),
+    };
+
+    let violations = check_client(7, &client, false);
+    assert_eq!(
+        violations,
+        vec![
+            InvariantViolation::NegativeHeld {
+                client: 7,
+                held: dec!(-1),
+            },
+            InvariantViolation::HeldMismatch {
+                client: 7,
+                held: dec!(-1),
+                open_disputes: vec![(1, dec!(1)), (2, dec!(2))],
+            },
+        ]
+    );
+    assert_eq!(
+        violations[1].to_string(),
+        "client 7: held amount -1 differs from 3 of open deposit disputes [tx 1: 1, tx 2: 2]"
+    );
+}
+
+#[test]
+fn test_lock_violations() {
+    let locked = Client {
+        locked: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        check_client(1, &locked, false),
+        vec![InvariantViolation::LockedWithoutChargeback { client: 1 }]
+    );
+    assert_eq!(check_client(1, &locked, true), vec![]);
+
+    let charged_back = Client {
+        available: dec!(-1),
+        held: Decimal::ZERO,
+        locked: false,
+        transactions: HashMap::from([(
+            4,
+            StoredTransaction {
+                tx_type: TransactionType::Deposit,
+                amount: dec!(1),
+                dispute_state: DisputeState::Chargeback,
+            },
+        )]),
+    };
+    assert_eq!(
+        check_client(1, &charged_back, false),
+        vec![InvariantViolation::ChargebackNotLocked { client: 1, tx: 4 }]
+    );
+}
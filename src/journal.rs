@@ -0,0 +1,185 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::{
+    limits::WithdrawalHistory,
+    types::{Amount, ClientId, EngineError, StoredTransaction, TransactionId, TransactionType},
+};
+
+/// State touched by one processed transaction as it was before, enough to revert it.
+///
+/// Transaction changes only its own client, so only state of that client is kept.
+pub(crate) struct UndoStep {
+    pub(crate) sequence: u64,
+    /// Sequence number of change feed
+    pub(crate) changes: u64,
+    /// Length of ledger postings and flagged transactions, both are only appended
+    pub(crate) postings: usize,
+    pub(crate) flagged: usize,
+    pub(crate) client_id: ClientId,
+    pub(crate) tx: TransactionId,
+    pub(crate) tx_type: TransactionType,
+    /// Available, held and locked, `None` when client did not exist
+    pub(crate) balances: Option<(Amount, Amount, bool)>,
+    /// Stored transaction `tx` of client
+    pub(crate) transaction: Option<StoredTransaction>,
+    /// `None` when client had no config entry
+    pub(crate) credit_limit: Option<Amount>,
+    pub(crate) withdrawal_history: Option<WithdrawalHistory>,
+    pub(crate) limit_breaches: Option<usize>,
+    pub(crate) locked_by_rule: Option<&'static str>,
+    /// Client state of each risk rule in order of rules
+    pub(crate) risk_rules: Vec<serde_json::Value>,
+}
+
+/// Undo steps of the last processed transactions and named checkpoints between them.
+///
+/// Steps of open batch are kept regardless of limit, until the batch is committed or rolled back.
+#[derive(Default)]
+pub(crate) struct Journal {
+    steps: VecDeque<UndoStep>,
+    /// Maximum number of kept steps, the oldest are dropped, 0 disables rollback
+    limit: usize,
+    /// Index of the first step of open batch
+    batch: Option<usize>,
+    /// Number of dropped steps, positions count all steps since the journal was cleared
+    dropped: u64,
+    checkpoints: HashMap<String, u64>,
+}
+
+impl Journal {
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.limit > 0 || self.in_batch()
+    }
+
+    pub(crate) fn in_batch(&self) -> bool {
+        self.batch.is_some()
+    }
+
+    pub(crate) fn begin(&mut self) -> Result<(), EngineError> {
+        if self.in_batch() {
+            return Err(EngineError::Batch("batch is already open".to_string()));
+        }
+        self.batch = Some(self.steps.len());
+        Ok(())
+    }
+
+    /// Client and transaction of each step of open batch, without duplicates.
+    pub(crate) fn batch_changes(&self) -> Result<Vec<(ClientId, TransactionId)>, EngineError> {
+        let start = self.batch_start()?;
+        let mut seen = HashSet::new();
+        Ok(self
+            .steps
+            .range(start..)
+            .map(|step| (step.client_id, step.tx))
+            .filter(|change| seen.insert(*change))
+            .collect())
+    }
+
+    /// Close open batch, its steps are kept within limit like any other.
+    pub(crate) fn commit(&mut self) -> Result<(), EngineError> {
+        self.batch_start()?;
+        self.batch = None;
+        self.trim();
+        Ok(())
+    }
+
+    /// Close open batch and remove all its steps, the newest first.
+    pub(crate) fn abort(&mut self) -> Result<Vec<UndoStep>, EngineError> {
+        let start = self.batch_start()?;
+        let steps = self.pop(self.steps.len() - start)?;
+        self.batch = None;
+        self.trim();
+        Ok(steps)
+    }
+
+    pub(crate) fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        self.trim();
+    }
+
+    pub(crate) fn push(&mut self, step: UndoStep) {
+        self.steps.push_back(step);
+        self.trim();
+    }
+
+    /// Forget all steps and checkpoints, e.g. after engine state was replaced.
+    pub(crate) fn clear(&mut self) {
+        self.steps.clear();
+        self.dropped = 0;
+        self.batch = None;
+        self.checkpoints.clear();
+    }
+
+    /// Name the current position, existing checkpoint of the same name is moved.
+    pub(crate) fn checkpoint(&mut self, name: &str) -> Result<(), EngineError> {
+        self.check_enabled()?;
+        self.checkpoints.insert(name.to_string(), self.position());
+        Ok(())
+    }
+
+    /// Number of steps since checkpoint `name`.
+    pub(crate) fn steps_since(&self, name: &str) -> Result<usize, EngineError> {
+        let position = *self
+            .checkpoints
+            .get(name)
+            .ok_or_else(|| EngineError::Rollback(format!("unknown checkpoint {}", name)))?;
+        if position < self.dropped {
+            return Err(EngineError::Rollback(format!(
+                "checkpoint {} is older than the last {} transactions",
+                name, self.limit
+            )));
+        }
+        Ok((self.position() - position) as usize)
+    }
+
+    /// Remove the last `count` steps, the newest first. Checkpoints after the new position are removed.
+    pub(crate) fn pop(&mut self, count: usize) -> Result<Vec<UndoStep>, EngineError> {
+        self.check_enabled()?;
+        let available = self.steps.len() - self.batch.unwrap_or(0);
+        if count > available {
+            return Err(EngineError::Rollback(match self.in_batch() {
+                true => format!(
+                    "only {} transactions of open batch can be rolled back",
+                    available
+                ),
+                false => format!("only {} transactions can be rolled back", available),
+            }));
+        }
+
+        let steps: Vec<_> = (0..count).filter_map(|_| self.steps.pop_back()).collect();
+        let position = self.position();
+        self.checkpoints
+            .retain(|_, checkpoint| *checkpoint <= position);
+        Ok(steps)
+    }
+
+    fn check_enabled(&self) -> Result<(), EngineError> {
+        match self.is_enabled() {
+            true => Ok(()),
+            false => Err(EngineError::Rollback("rollback is not enabled".to_string())),
+        }
+    }
+
+    fn batch_start(&self) -> Result<usize, EngineError> {
+        self.batch
+            .ok_or_else(|| EngineError::Batch("no batch is open".to_string()))
+    }
+
+    fn position(&self) -> u64 {
+        self.dropped + self.steps.len() as u64
+    }
+
+    fn trim(&mut self) {
+        if self.in_batch() {
+            return;
+        }
+        while self.steps.len() > self.limit {
+            self.steps.pop_front();
+            self.dropped += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "journal.test.rs"]
+mod tests;
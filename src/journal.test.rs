@@ -0,0 +1,286 @@
+use std::sync::mpsc;
+
+use rust_decimal_macros::dec;
+
+use crate::{
+    engine::Engine,
+    journal::{Journal, UndoStep},
+    snapshot::Snapshot,
+    testing::process,
+    types::{ClientId, EngineError, TransactionId, TransactionType},
+};
+
+const CONFIG: &str = r#"
+[withdrawal_limits]
+max_count = 2
+window = 10
+
+[fees]
+deposit_percent = "1.0"
+reverse_on_chargeback = true
+
+[risk_rules]
+immediate_withdrawal = "flag"
+lock_on_open_disputes = 2
+"#;
+
+fn engine(rollback_limit: usize) -> Engine {
+    let mut engine = Engine::with_config(toml::from_str(CONFIG).unwrap());
+    engine.set_rollback_limit(rollback_limit);
+    engine.allow_admin_transactions();
+    engine
+}
+
+/// Snapshot without change feed sequence, which keeps increasing over rollback.
+fn state(engine: &Engine) -> Snapshot {
+    Snapshot {
+        changes: 0,
+        ..engine.snapshot()
+    }
+}
+
+const BEFORE: &[&str] = &[
+    "deposit, 1, 1, 100.0",
+    "deposit, 2, 2, 10.0",
+    "dispute, 2, 2,",
+    "deposit, 3, 3, 1.0",
+    "deposit, 3, 4, 1.0",
+];
+
+const BATCH: &[&str] = &[
+    // Flagged by immediate withdrawal rule
+    "withdrawal, 1, 5, 50.0",
+    "limit, 1, 6, 100.0",
+    "withdrawal, 1, 7, 120.0",
+    // Rejected by withdrawal count in window
+    "withdrawal, 1, 8, 1.0",
+    "chargeback, 2, 2,",
+    // Locked by open disputes rule
+    "dispute, 3, 3,",
+    "dispute, 3, 4,",
+    "deposit, 4, 9, 7.0",
+];
+
+#[test]
+fn test_rollback_restores_state_exactly() {
+    let mut expected = engine(0);
+    process(&mut expected, BEFORE);
+
+    let mut engine = engine(100);
+    process(&mut engine, BEFORE);
+    process(&mut engine, BATCH);
+    assert!(engine.client(2).unwrap().locked);
+    assert!(engine.client(3).unwrap().locked);
+
+    engine.rollback_steps(BATCH.len()).unwrap();
+    assert_eq!(state(&engine), state(&expected));
+    engine.verify_ledger().unwrap();
+
+    // Both continue the same way, including credit limit which is reset
+    process(&mut engine, BATCH);
+    process(&mut expected, BATCH);
+    assert_eq!(state(&engine), state(&expected));
+    assert_eq!(engine.client_balances(), expected.client_balances());
+}
+
+#[test]
+fn test_rollback_step_by_step() {
+    let mut engine = engine(100);
+    let mut snapshots = Vec::new();
+    for line in BEFORE.iter().chain(BATCH) {
+        snapshots.push(state(&engine));
+        process(&mut engine, &[line]);
+    }
+
+    while let Some(snapshot) = snapshots.pop() {
+        engine.rollback_steps(1).unwrap();
+        assert_eq!(state(&engine), snapshot);
+        engine.verify_ledger().unwrap();
+    }
+    assert!(engine.client_balances().is_empty());
+}
+
+#[test]
+fn test_rollback_to_checkpoint() {
+    let mut engine = engine(100);
+    process(&mut engine, BEFORE);
+    engine.checkpoint("batch").unwrap();
+    let expected = state(&engine);
+
+    process(&mut engine, &BATCH[..3]);
+    engine.checkpoint("middle").unwrap();
+    process(&mut engine, &BATCH[3..]);
+
+    assert_eq!(engine.rollback_to("batch").unwrap(), BATCH.len());
+    assert_eq!(state(&engine), expected);
+    // Checkpoint after the restored state is gone, the restored one stays
+    assert!(matches!(
+        engine.rollback_to("middle"),
+        Err(EngineError::Rollback(message)) if message == "unknown checkpoint middle"
+    ));
+    assert_eq!(engine.rollback_to("batch").unwrap(), 0);
+}
+
+#[test]
+fn test_rollback_limit() {
+    let mut engine = engine(3);
+    engine.checkpoint("start").unwrap();
+    process(&mut engine, BEFORE);
+
+    assert!(matches!(
+        engine.rollback_steps(4),
+        Err(EngineError::Rollback(message)) if message == "only 3 transactions can be rolled back"
+    ));
+    assert!(matches!(
+        engine.rollback_to("start"),
+        Err(EngineError::Rollback(message)) if message == "checkpoint start is older than the last 3 transactions"
+    ));
+    engine.rollback_steps(3).unwrap();
+    assert_eq!(engine.client_balances().len(), 2);
+
+    let mut disabled = Engine::new();
+    process(&mut disabled, BEFORE);
+    assert!(matches!(
+        disabled.rollback_steps(1),
+        Err(EngineError::Rollback(message)) if message == "rollback is not enabled"
+    ));
+    assert!(disabled.checkpoint("start").is_err());
+}
+
+#[test]
+fn test_rollback_publishes_compensating_changes() {
+    let mut engine = engine(100);
+    let (sender, receiver) = mpsc::channel();
+    engine.subscribe(Box::new(sender));
+    process(&mut engine, BEFORE);
+    let before: Vec<_> = receiver.try_iter().collect();
+    process(
+        &mut engine,
+        &["withdrawal, 1, 5, 50.0", "deposit, 5, 6, 1.0"],
+    );
+    let sent = receiver.try_iter().last().unwrap().sequence;
+
+    engine.rollback_steps(2).unwrap();
+    let changes: Vec<_> = receiver.try_iter().collect();
+
+    // Sent sequence numbers are never reused, clients are back to the state before the first reverted transaction
+    let sequences: Vec<_> = changes.iter().map(|change| change.sequence).collect();
+    assert_eq!(sequences, [sent + 1, sent + 2]);
+    assert!(changes.iter().all(|change| change.rolled_back));
+    assert_eq!((changes[0].client, changes[0].tx), (1, 5));
+    assert_eq!(changes[0].available, before[0].available);
+    assert_eq!((changes[1].client, changes[1].tx), (5, 6));
+    assert_eq!(changes[1].total, dec!(0));
+
+    process(&mut engine, &["deposit, 1, 7, 1.0"]);
+    assert_eq!(receiver.try_recv().unwrap().sequence, sent + 3);
+}
+
+#[test]
+fn test_batch_commit_and_rollback() {
+    let mut engine = engine(0);
+    let (sender, receiver) = mpsc::channel();
+    engine.subscribe(Box::new(sender));
+    process(&mut engine, BEFORE);
+    let expected = engine.snapshot();
+    let published = receiver.try_iter().count();
+
+    engine.begin().unwrap();
+    process(&mut engine, BATCH);
+    assert!(engine.client(4).is_some());
+    assert_eq!(receiver.try_iter().count(), 0);
+    engine.rollback().unwrap();
+
+    assert_eq!(engine.snapshot(), expected);
+    assert_eq!(receiver.try_iter().count(), 0);
+    engine.verify_ledger().unwrap();
+
+    engine.begin().unwrap();
+    process(&mut engine, BATCH);
+    engine.commit().unwrap();
+
+    // Changes are numbered continuously, the rolled back ones are never seen
+    let sequences: Vec<_> = receiver.try_iter().map(|change| change.sequence).collect();
+    assert_eq!(sequences.first(), Some(&(published as u64 + 1)));
+    assert_eq!(
+        sequences.len(),
+        engine.snapshot().changes as usize - published
+    );
+    // Committed batch cannot be rolled back without rollback limit
+    assert!(engine.rollback_steps(1).is_err());
+}
+
+#[test]
+fn test_batch_errors() {
+    let mut engine = engine(100);
+    process(&mut engine, BEFORE);
+
+    assert!(matches!(
+        engine.commit(),
+        Err(EngineError::Batch(message)) if message == "no batch is open"
+    ));
+    assert!(engine.rollback().is_err());
+
+    engine.begin().unwrap();
+    assert!(matches!(
+        engine.begin(),
+        Err(EngineError::Batch(message)) if message == "batch is already open"
+    ));
+    process(&mut engine, &BATCH[..2]);
+    // Steps within batch can be rolled back, not those before it
+    assert!(matches!(
+        engine.rollback_steps(3),
+        Err(EngineError::Rollback(message)) if message == "only 2 transactions of open batch can be rolled back"
+    ));
+    engine.rollback_steps(1).unwrap();
+    engine.commit().unwrap();
+
+    // Steps of committed batch are kept within rollback limit
+    engine.rollback_steps(BEFORE.len() + 1).unwrap();
+    assert!(engine.client_balances().is_empty());
+}
+
+fn step(client_id: ClientId, tx: TransactionId) -> UndoStep {
+    UndoStep {
+        sequence: 0,
+        changes: 0,
+        postings: 0,
+        flagged: 0,
+        client_id,
+        tx,
+        tx_type: TransactionType::Deposit,
+        balances: None,
+        transaction: None,
+        credit_limit: None,
+        withdrawal_history: None,
+        limit_breaches: None,
+        locked_by_rule: None,
+        risk_rules: Vec::new(),
+    }
+}
+
+#[test]
+fn test_batch_changes_in_first_seen_order() {
+    let mut journal = Journal::default();
+    journal.push(step(9, 9));
+    journal.begin().unwrap();
+    for (client_id, tx) in [(2, 1), (1, 1), (2, 1), (1, 2), (1, 1)] {
+        journal.push(step(client_id, tx));
+    }
+    assert_eq!(journal.batch_changes().unwrap(), [(2, 1), (1, 1), (1, 2)]);
+    journal.commit().unwrap();
+
+    // Large batch, each transaction is disputed and resolved
+    journal.begin().unwrap();
+    for tx in 0..100_000 {
+        for _ in 0..3 {
+            journal.push(step((tx % 100) as ClientId, tx));
+        }
+    }
+    let changes = journal.batch_changes().unwrap();
+    assert_eq!(changes.len(), 100_000);
+    assert!(changes
+        .iter()
+        .zip(0..)
+        .all(|((_, tx), expected)| *tx == expected));
+}
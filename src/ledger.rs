@@ -0,0 +1,217 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    str::FromStr,
+};
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::types::{Amount, ClientId, EngineError, TransactionId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Account {
+    Available(ClientId),
+    Held(ClientId),
+    /// Counterparty of money entering (deposit) and leaving (withdrawal, chargeback) the system
+    Settlement,
+    /// Collected fees
+    House,
+}
+
+impl fmt::Display for Account {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Account::Available(client_id) => write!(f, "available:{}", client_id),
+            Account::Held(client_id) => write!(f, "held:{}", client_id),
+            Account::Settlement => write!(f, "settlement"),
+            Account::House => write!(f, "house"),
+        }
+    }
+}
+
+impl FromStr for Account {
+    type Err = String;
+
+    /// Parse account written by `Display`, e.g. `available:1`.
+    fn from_str(account: &str) -> Result<Self, Self::Err> {
+        let client_id = |client_id: &str| {
+            client_id
+                .parse()
+                .map_err(|_| format!("invalid account {}", account))
+        };
+        match account.split_once(':') {
+            Some(("available", client_id_text)) => {
+                Ok(Account::Available(client_id(client_id_text)?))
+            }
+            Some(("held", client_id_text)) => Ok(Account::Held(client_id(client_id_text)?)),
+            None if account == "settlement" => Ok(Account::Settlement),
+            None if account == "house" => Ok(Account::House),
+            _ => Err(format!("invalid account {}", account)),
+        }
+    }
+}
+
+/// Balanced double entry: `amount` is subtracted from `from` account and added to `to` account.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Posting {
+    pub(crate) tx: TransactionId,
+    pub(crate) from: Account,
+    pub(crate) to: Account,
+    pub(crate) amount: Amount,
+}
+
+/// Journal of all postings with running account balances.
+#[derive(Debug, Default)]
+pub(crate) struct Ledger {
+    postings: Vec<Posting>,
+    balances: HashMap<Account, Amount>,
+    /// Fees of each client, postings from client available to house reduced by those back
+    client_fees: HashMap<ClientId, Amount>,
+    /// Fees charged to client for transaction, not reduced by reversal
+    charged_fees: HashMap<(ClientId, TransactionId), Amount>,
+}
+
+impl Ledger {
+    pub(crate) fn post(&mut self, tx: TransactionId, from: Account, to: Account, amount: Amount) {
+        *self.balances.entry(from).or_default() -= amount;
+        *self.balances.entry(to).or_default() += amount;
+        let posting = Posting {
+            tx,
+            from,
+            to,
+            amount,
+        };
+        self.index_fee(&posting, amount);
+        self.postings.push(posting);
+    }
+
+    /// Add `amount` to fee totals of fee posting, negative amount reverts the posting.
+    fn index_fee(&mut self, posting: &Posting, amount: Amount) {
+        match (posting.from, posting.to) {
+            (Account::Available(client_id), Account::House) => {
+                *self.client_fees.entry(client_id).or_default() += amount;
+                let charged = self
+                    .charged_fees
+                    .entry((client_id, posting.tx))
+                    .or_default();
+                *charged += amount;
+                if charged.is_zero() {
+                    self.charged_fees.remove(&(client_id, posting.tx));
+                }
+            }
+            (Account::House, Account::Available(client_id)) => {
+                *self.client_fees.entry(client_id).or_default() -= amount;
+            }
+            _ => {}
+        }
+    }
+
+    /// Remove postings after the first `len` ones and revert their amounts.
+    ///
+    /// Balances end up the same as after replaying the kept postings, accounts without kept postings are removed.
+    pub(crate) fn truncate(&mut self, len: usize) {
+        if len >= self.postings.len() {
+            return;
+        }
+
+        let mut zero = HashSet::new();
+        for posting in self.postings.split_off(len).into_iter().rev() {
+            *self.balances.entry(posting.from).or_default() += posting.amount;
+            *self.balances.entry(posting.to).or_default() -= posting.amount;
+            self.index_fee(&posting, -posting.amount);
+            zero.extend([posting.from, posting.to]);
+        }
+
+        zero.retain(|account| self.balances[account].is_zero());
+        for posting in &self.postings {
+            if zero.is_empty() {
+                break;
+            }
+            zero.remove(&posting.from);
+            zero.remove(&posting.to);
+        }
+        for account in zero {
+            self.balances.remove(&account);
+        }
+    }
+
+    pub(crate) fn balance(&self, account: Account) -> Amount {
+        self.balances
+            .get(&account)
+            .copied()
+            .unwrap_or(Decimal::ZERO)
+    }
+
+    /// Total fees charged to clients, reduced by reversed fees.
+    pub(crate) fn client_fees(&self) -> &HashMap<ClientId, Amount> {
+        &self.client_fees
+    }
+
+    /// Fees charged to client for transaction, including those reversed since.
+    pub(crate) fn charged_fee(&self, client_id: ClientId, tx: TransactionId) -> Amount {
+        self.charged_fees
+            .get(&(client_id, tx))
+            .copied()
+            .unwrap_or(Decimal::ZERO)
+    }
+
+    pub(crate) fn postings(&self) -> &[Posting] {
+        &self.postings
+    }
+
+    /// All account balances ordered by account.
+    pub(crate) fn trial_balance(&self) -> Vec<(Account, Amount)> {
+        let mut trial_balance: Vec<_> = self
+            .balances
+            .iter()
+            .map(|(account, balance)| (*account, *balance))
+            .collect();
+        trial_balance.sort_by_key(|(account, _)| *account);
+        trial_balance
+    }
+
+    /// Replay all postings and check that the result matches running balances and sums to zero.
+    pub(crate) fn verify(&self) -> Result<(), EngineError> {
+        let mut balances: HashMap<Account, Amount> = HashMap::new();
+        for posting in &self.postings {
+            *balances.entry(posting.from).or_default() -= posting.amount;
+            *balances.entry(posting.to).or_default() += posting.amount;
+        }
+
+        if balances != self.balances {
+            return Err(EngineError::LedgerImbalance(
+                "running balances do not match postings".to_string(),
+            ));
+        }
+
+        let mut replayed = Ledger::default();
+        for posting in &self.postings {
+            replayed.index_fee(posting, posting.amount);
+        }
+        let nonzero = |fees: &HashMap<ClientId, Amount>| -> HashMap<ClientId, Amount> {
+            fees.iter()
+                .filter(|(_, fee)| !fee.is_zero())
+                .map(|(client_id, fee)| (*client_id, *fee))
+                .collect()
+        };
+        if nonzero(&replayed.client_fees) != nonzero(&self.client_fees)
+            || replayed.charged_fees != self.charged_fees
+        {
+            return Err(EngineError::LedgerImbalance(
+                "fee totals do not match postings".to_string(),
+            ));
+        }
+
+        let total: Amount = balances.values().sum();
+        if !total.is_zero() {
+            return Err(EngineError::LedgerImbalance(format!(
+                "balances sum to {}",
+                total
+            )));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::{Amount, RejectReason};
+
+/// Risk limits of withdrawals. Unset fields are not checked.
+///
+/// Input has no timestamps, so the window is measured in number of transactions processed by engine.
+#[derive(Debug, Default, Clone, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct WithdrawalLimits {
+    /// Maximum amount of a single withdrawal
+    #[serde(default, with = "rust_decimal::serde::str_option")]
+    pub(crate) max_amount: Option<Amount>,
+    /// Maximum sum of withdrawals within the window
+    #[serde(default, with = "rust_decimal::serde::str_option")]
+    pub(crate) max_total: Option<Amount>,
+    /// Maximum number of withdrawals within the window
+    pub(crate) max_count: Option<usize>,
+    /// Window size in processed transactions, whole run if not set
+    pub(crate) window: Option<u64>,
+}
+
+impl WithdrawalLimits {
+    /// Fields set in `self` take precedence over `defaults`.
+    pub(crate) fn or(&self, defaults: &Self) -> Self {
+        Self {
+            max_amount: self.max_amount.or(defaults.max_amount),
+            max_total: self.max_total.or(defaults.max_total),
+            max_count: self.max_count.or(defaults.max_count),
+            window: self.window.or(defaults.window),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.max_amount.is_none() && self.max_total.is_none() && self.max_count.is_none()
+    }
+}
+
+/// Accepted withdrawals of one client as `(sequence number, amount)`, oldest first.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct WithdrawalHistory(VecDeque<(u64, Amount)>);
+
+impl WithdrawalHistory {
+    /// Check withdrawal of `amount` processed as transaction number `sequence` against `limits`.
+    pub(crate) fn check(
+        &mut self,
+        limits: &WithdrawalLimits,
+        sequence: u64,
+        amount: Amount,
+    ) -> Result<(), RejectReason> {
+        if let Some(window) = limits.window {
+            while let Some(&(oldest, _)) = self.0.front() {
+                if oldest + window > sequence {
+                    break;
+                }
+                self.0.pop_front();
+            }
+        }
+
+        if limits
+            .max_amount
+            .is_some_and(|max_amount| amount > max_amount)
+        {
+            return Err(RejectReason::WithdrawalAmountExceeded);
+        }
+
+        if let Some(max_total) = limits.max_total {
+            let total: Amount = self.0.iter().map(|(_, amount)| amount).sum();
+            if total + amount > max_total {
+                return Err(RejectReason::WithdrawalTotalExceeded);
+            }
+        }
+
+        if limits
+            .max_count
+            .is_some_and(|max_count| self.0.len() >= max_count)
+        {
+            return Err(RejectReason::WithdrawalCountExceeded);
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn record(&mut self, sequence: u64, amount: Amount) {
+        self.0.push_back((sequence, amount));
+    }
+}
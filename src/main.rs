@@ -1,22 +1,235 @@
+mod cli;
+#[cfg(feature = "arrow")]
+mod columnar;
+mod config;
+#[cfg(feature = "kafka")]
+mod consumer;
+mod dialect;
+mod diff;
 mod engine;
+mod feed;
+mod fees;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod input;
+mod invariants;
+mod journal;
+mod ledger;
+mod limits;
+mod mapping;
+mod merge;
+mod output;
+#[cfg(feature = "postgres")]
+mod postgresql;
+mod repl;
+mod risk;
+mod server;
+mod snapshot;
+mod sqlite;
+mod stats;
+mod store;
+mod tcp;
+#[cfg(test)]
+mod testing;
 mod types;
+mod validate;
 
-use crate::engine::Engine;
+use std::{
+    fs::OpenOptions,
+    io::{self, Write},
+    process::ExitCode,
+};
 
-fn main() {
-    let filename = std::env::args().nth(1);
+use clap::Parser;
 
-    if filename == None {
-        println!("Error occured: Missing filename argument");
-        std::process::exit(1);
+use crate::{
+    cli::{Cli, Command, EngineArgs, ProcessArgs, StateArgs},
+    config::EngineConfig,
+    engine::Engine,
+    feed::JsonLines,
+    types::EngineError,
+};
+
+/// Engine with config and invariant check of arguments.
+fn engine(args: &EngineArgs) -> Result<Engine, EngineError> {
+    let mut engine = match &args.config {
+        Some(config) => Engine::with_config(EngineConfig::from_path(config)?),
+        None => Engine::new(),
+    };
+    engine.set_invariant_check(args.check_invariants.unwrap_or_default());
+    Ok(engine)
+}
+
+/// Engine resumed from database and publishing changes, as requested by arguments.
+fn stateful_engine(args: &EngineArgs, state: &StateArgs) -> Result<Engine, EngineError> {
+    let mut engine = engine(args)?;
+    if let Some(database) = &state.database {
+        engine.open_store(store::open(database)?)?;
     }
+    if let Some(changes) = &state.changes {
+        subscribe_changes(&mut engine, changes)?;
+    }
+    if let Some(limit) = state.rollback_limit {
+        engine.set_rollback_limit(limit);
+    }
+    Ok(engine)
+}
 
-    let mut engine = Engine::new();
+/// Append account changes as JSON lines to file, `-` for stdout.
+fn subscribe_changes(engine: &mut Engine, changes: &str) -> Result<(), EngineError> {
+    let writer: Box<dyn Write> = match changes {
+        "-" => Box::new(io::stdout()),
+        filename => Box::new(
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(filename)?,
+        ),
+    };
+    engine.subscribe(Box::new(JsonLines(writer)));
+    Ok(())
+}
+
+fn process(engine_args: &EngineArgs, args: ProcessArgs) -> Result<(), EngineError> {
+    args.validate()?;
+    let input = args.input.options()?;
+    let mut engine = stateful_engine(engine_args, &args.state)?;
+    if args.admin {
+        engine.allow_admin_transactions();
+    }
+
+    let rolled_back = match args.atomic {
+        true => engine.read_and_process_atomically(&args.files, &input)?,
+        false => {
+            engine.read_and_process_input(&args.files, &input)?;
+            Vec::new()
+        }
+    };
+    for err in &rolled_back {
+        eprintln!("Rolled back {}", err);
+    }
+    engine.verify_ledger()?;
 
-    if let Err(err) = engine.read_and_process_input(&filename.unwrap()) {
-        println!("Error occured: {}", err);
-        std::process::exit(1);
+    if args.trial_balance {
+        engine.print_trial_balance();
+    } else {
+        engine.print_clients(args.output_format)?;
+        engine.print_summary();
     }
 
-    engine.print_clients();
+    match rolled_back.len() {
+        0 => Ok(()),
+        inputs => Err(EngineError::RolledBack(inputs)),
+    }
+}
+
+fn run(cli: Cli) -> Result<(), EngineError> {
+    let Cli {
+        command,
+        process: process_args,
+        engine: engine_args,
+    } = cli;
+
+    match command.unwrap_or(Command::Process(process_args)) {
+        Command::Process(args) => process(&engine_args, args),
+        Command::Validate { input, file } => {
+            engine_args.reject("validate")?;
+            let report = validate::validate(&file, &input.options()?);
+            for (record, problem) in &report.problems {
+                match record {
+                    0 => println!("input: {}", problem),
+                    _ => println!("record {}: {}", record, problem),
+                }
+            }
+            println!("Checked {} records", report.records);
+
+            match report.problems.len() {
+                0 => Ok(()),
+                problems => Err(EngineError::ValidationFailed(problems)),
+            }
+        }
+        Command::Diff { input, left, right } => {
+            let input = input.options()?;
+            let mut balances = Vec::new();
+            for filename in [left, right] {
+                let mut engine = engine(&engine_args)?;
+                engine.read_and_process_input(std::slice::from_ref(&filename), &input)?;
+                balances.push(engine.client_balances());
+            }
+
+            let differences = diff::diff(&balances[0], &balances[1]);
+            for difference in &differences {
+                println!("{}", difference);
+            }
+            match differences.len() {
+                0 => Ok(()),
+                count => Err(EngineError::BalancesDiffer(count)),
+            }
+        }
+        Command::Stats { input, files } => {
+            let input = input.options()?;
+            let mut engine = engine(&engine_args)?;
+            print!("{}", stats::collect(&mut engine, &files, &input)?);
+            Ok(())
+        }
+        Command::Repl {
+            input,
+            changes,
+            rollback_limit,
+            files,
+        } => {
+            let input = input.options()?;
+            let mut engine = engine(&engine_args)?;
+            if let Some(changes) = &changes {
+                subscribe_changes(&mut engine, changes)?;
+            }
+            engine.read_and_process_input(&files, &input)?;
+            // Shell can undo only its own transactions, not those of input files
+            engine.set_rollback_limit(rollback_limit.unwrap_or(usize::MAX));
+            repl::run(&mut engine)
+        }
+        Command::Serve { listen, state } => {
+            server::serve(&mut stateful_engine(&engine_args, &state)?, &listen)
+        }
+        Command::Listen { listen, state } => {
+            tcp::listen(&mut stateful_engine(&engine_args, &state)?, &listen)
+        }
+        #[cfg(feature = "grpc")]
+        Command::ServeGrpc { listen, state } => {
+            grpc::serve(&mut stateful_engine(&engine_args, &state)?, &listen)
+        }
+        #[cfg(not(feature = "grpc"))]
+        Command::ServeGrpc { .. } => Err(EngineError::Usage(
+            "serve-grpc requires build with grpc feature".to_string(),
+        )),
+        #[cfg(feature = "kafka")]
+        Command::Consume {
+            brokers,
+            topic,
+            group,
+            checkpoint,
+            state,
+        } => consumer::consume(
+            &mut stateful_engine(&engine_args, &state)?,
+            &brokers,
+            &topic,
+            &group,
+            &checkpoint,
+        ),
+        #[cfg(not(feature = "kafka"))]
+        Command::Consume { .. } => Err(EngineError::Usage(
+            "consume requires build with kafka feature".to_string(),
+        )),
+    }
+}
+
+/// Usage errors exit with code 2 by clap, other failures with code of [`cli::exit_code`].
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error occured: {}", err);
+            ExitCode::from(cli::exit_code(&err))
+        }
+    }
 }
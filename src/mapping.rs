@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+
+use serde::{de::IntoDeserializer, Deserialize};
+
+use crate::{
+    dialect::{ColumnMapping, CsvDialect},
+    types::{EngineError, TransactionType},
+};
+
+/// Partner specific schema of CSV input, e.g.
+///
+/// ```toml
+/// [acme]
+/// columns = { type = "kind", client = "account", tx = "id", amount = "value" }
+/// types = { credit = "deposit", debit = "withdrawal" }
+/// ```
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct PartnerMapping {
+    pub(crate) columns: Option<ColumnMapping>,
+    /// Partner type token to transaction type
+    pub(crate) types: HashMap<String, String>,
+}
+
+impl PartnerMapping {
+    /// Load mapping of partner from file with one table per partner.
+    pub(crate) fn from_path(filename: &str, partner: &str) -> Result<Self, EngineError> {
+        let content = std::fs::read_to_string(filename)?;
+        Self::parse(&content, partner)
+    }
+
+    pub(crate) fn parse(content: &str, partner: &str) -> Result<Self, EngineError> {
+        let mut partners: HashMap<String, Self> = toml::from_str(content)?;
+        let mapping = partners
+            .remove(partner)
+            .ok_or_else(|| EngineError::InvalidMapping(format!("partner {} not found", partner)))?;
+
+        for (token, tx_type) in &mapping.types {
+            TransactionType::deserialize(tx_type.as_str().into_deserializer()).map_err(
+                |err: serde::de::value::Error| {
+                    EngineError::InvalidMapping(format!("type {}: {}", token, err))
+                },
+            )?;
+        }
+
+        Ok(mapping)
+    }
+
+    /// Set columns and type translations of dialect, columns already set conflict with the mapping.
+    pub(crate) fn apply(self, dialect: &mut CsvDialect) -> Result<(), EngineError> {
+        if self.columns.is_some() {
+            if dialect.columns.is_some() {
+                return Err(EngineError::InvalidMapping(
+                    "columns are set both by mapping and dialect".to_string(),
+                ));
+            }
+            dialect.columns = self.columns;
+        }
+        dialect.type_names = self.types;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[path = "mapping.test.rs"]
+mod tests;
@@ -0,0 +1,105 @@
+use rust_decimal_macros::dec;
+
+use crate::{
+    dialect::{read_csv, Column, ColumnMapping, CsvDialect},
+    mapping::PartnerMapping,
+    types::{EngineError, Transaction, TransactionType},
+};
+
+const MAPPINGS: &str = r#"
+[acme]
+columns = { type = "kind", client = "account", tx = "id", amount = "value" }
+types = { credit = "deposit", debit = "withdrawal" }
+
+[globex]
+columns = { client = 0, tx = 1 }
+"#;
+
+#[test]
+fn test_parse_mapping() {
+    let mapping = PartnerMapping::parse(MAPPINGS, "globex").unwrap();
+    assert_eq!(
+        mapping.columns,
+        Some(ColumnMapping {
+            tx_type: Column::Name("type".to_string()),
+            client: Column::Index(0),
+            tx: Column::Index(1),
+            amount: Column::Name("amount".to_string()),
+        })
+    );
+    assert!(mapping.types.is_empty());
+
+    assert!(matches!(
+        PartnerMapping::parse(MAPPINGS, "initech"),
+        Err(EngineError::InvalidMapping(_))
+    ));
+    assert!(matches!(
+        PartnerMapping::parse("[acme]\ntypes = { credit = \"payment\" }", "acme"),
+        Err(EngineError::InvalidMapping(_))
+    ));
+    assert!(matches!(
+        PartnerMapping::parse("[acme]\ncolumns = { kind = \"type\" }", "acme"),
+        Err(EngineError::ConfigParse(_))
+    ));
+}
+
+#[test]
+fn test_apply_mapping() {
+    let mut dialect = CsvDialect::default();
+    PartnerMapping::parse(MAPPINGS, "acme")
+        .unwrap()
+        .apply(&mut dialect)
+        .unwrap();
+
+    let input = "id, account, kind, value\n1, 1, credit, 2.5\n2, 1, debit, 1.0\n3, 1, deposit, 1\n";
+    let transactions: Vec<Transaction> = read_csv(Box::new(input.as_bytes()), &dialect)
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        transactions
+            .iter()
+            .map(|transaction| (&transaction.tx_type, transaction.tx, transaction.amount))
+            .collect::<Vec<_>>(),
+        vec![
+            (&TransactionType::Deposit, 1, Some(dec!(2.5))),
+            (&TransactionType::Withdrawal, 2, Some(dec!(1.0))),
+            (&TransactionType::Deposit, 3, Some(dec!(1))),
+        ]
+    );
+
+    let mut dialect = CsvDialect {
+        columns: Some(ColumnMapping::parse("0,1,2,3").unwrap()),
+        ..Default::default()
+    };
+    assert!(matches!(
+        PartnerMapping::parse(MAPPINGS, "acme")
+            .unwrap()
+            .apply(&mut dialect),
+        Err(EngineError::InvalidMapping(_))
+    ));
+}
+
+#[test]
+fn test_type_names_without_columns() {
+    let mut dialect = CsvDialect::default();
+    PartnerMapping::parse("[acme]\ntypes = { credit = \"deposit\" }", "acme")
+        .unwrap()
+        .apply(&mut dialect)
+        .unwrap();
+
+    let input = "client, type, amount, tx\n1, credit, 2.5, 1\n";
+    let transactions: Vec<Transaction> = read_csv(Box::new(input.as_bytes()), &dialect)
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        transactions,
+        vec![Transaction {
+            tx_type: TransactionType::Deposit,
+            client: 1,
+            tx: 1,
+            amount: Some(dec!(2.5)),
+        }]
+    );
+}
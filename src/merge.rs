@@ -0,0 +1,122 @@
+use std::fmt;
+
+use crate::{
+    input::{KeyedTransactions, TaggedTransactions},
+    types::{EngineError, Transaction},
+};
+
+/// Value of merge column, integers (sequence numbers) are compared as numbers,
+/// anything else (e.g. ISO 8601 timestamps) as text.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum MergeKey {
+    Number(u64),
+    Text(String),
+}
+
+impl fmt::Display for MergeKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MergeKey::Number(number) => write!(f, "{}", number),
+            MergeKey::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+impl MergeKey {
+    fn parse(key: String) -> Self {
+        match key.parse() {
+            Ok(number) => MergeKey::Number(number),
+            Err(_) => MergeKey::Text(key),
+        }
+    }
+}
+
+struct Source {
+    input: KeyedTransactions,
+    next: Option<(MergeKey, Transaction)>,
+    /// Key of last merged transaction
+    last: Option<MergeKey>,
+    done: bool,
+}
+
+/// Merge inputs ordered by key into one stream ordered by key, equal keys keep order of inputs.
+///
+/// Each input must be ordered by key itself, otherwise merging fails.
+pub(crate) fn merge(inputs: Vec<Result<KeyedTransactions, EngineError>>) -> TaggedTransactions {
+    let mut errors = Vec::new();
+    let mut sources = Vec::new();
+    for (index, input) in inputs.into_iter().enumerate() {
+        match input {
+            Ok(input) => sources.push(Source {
+                input,
+                next: None,
+                last: None,
+                done: false,
+            }),
+            Err(err) => {
+                errors.push((index, Err(err)));
+                sources.push(Source {
+                    input: Box::new(std::iter::empty()),
+                    next: None,
+                    last: None,
+                    done: true,
+                });
+            }
+        }
+    }
+
+    Box::new(errors.into_iter().chain(Merge { sources }))
+}
+
+struct Merge {
+    sources: Vec<Source>,
+}
+
+impl Iterator for Merge {
+    type Item = (usize, Result<Transaction, EngineError>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for (index, source) in self.sources.iter_mut().enumerate() {
+            if source.next.is_some() || source.done {
+                continue;
+            }
+            match source.input.next() {
+                Some(Ok((key, transaction))) => {
+                    let key = MergeKey::parse(key);
+                    if let Some(last) = source.last.as_ref().filter(|last| key < **last) {
+                        let err = EngineError::InvalidMerge(format!(
+                            "input is not ordered, {} follows {}",
+                            key, last
+                        ));
+                        source.done = true;
+                        return Some((index, Err(err)));
+                    }
+                    source.next = Some((key, transaction));
+                }
+                Some(Err(err)) => {
+                    source.done = true;
+                    return Some((index, Err(err)));
+                }
+                None => source.done = true,
+            }
+        }
+
+        let index = self
+            .sources
+            .iter()
+            .enumerate()
+            .filter_map(|(index, source)| Some((index, &source.next.as_ref()?.0)))
+            .min_by(|(_, a), (_, b)| a.cmp(b))?
+            .0;
+
+        let source = &mut self.sources[index];
+        let (key, transaction) = source.next.take()?;
+        source.last = Some(key);
+
+        Some((index, Ok(transaction)))
+    }
+}
+
+#[cfg(test)]
+#[path = "merge.test.rs"]
+mod tests;
@@ -0,0 +1,93 @@
+use crate::{
+    dialect::{read_csv_keyed, Column, CsvDialect},
+    input::KeyedTransactions,
+    merge::merge,
+    types::EngineError,
+};
+
+fn keyed(input: &'static str) -> Result<KeyedTransactions, EngineError> {
+    read_csv_keyed(
+        Box::new(input.as_bytes()),
+        &CsvDialect::default(),
+        &Column::Name("seq".to_string()),
+    )
+}
+
+/// Input index and tx id of merged transactions
+fn merged(inputs: Vec<Result<KeyedTransactions, EngineError>>) -> Vec<(usize, u32)> {
+    merge(inputs)
+        .map(|(index, result)| (index, result.unwrap().tx))
+        .collect()
+}
+
+#[test]
+fn test_merge_by_sequence() {
+    let a = "seq, type, client, tx, amount\n1, deposit, 1, 1, 1.0\n10, deposit, 1, 4, 1.0\n";
+    let b = "seq, type, client, tx, amount\n2, deposit, 1, 2, 1.0\n3, deposit, 1, 3, 1.0\n";
+    let c = "seq, type, client, tx, amount\n";
+
+    assert_eq!(
+        merged(vec![keyed(a), keyed(b), keyed(c)]),
+        vec![(0, 1), (1, 2), (1, 3), (0, 4)]
+    );
+}
+
+#[test]
+fn test_merge_by_timestamp() {
+    let a = "type, client, tx, amount, time\ndeposit, 1, 1, 1.0, 2024-03-01T10:00:00Z\ndeposit, 1, 3, 1.0, 2024-03-01T12:00:00Z\n";
+    let b = "time, type, client, tx, amount\n2024-03-01T10:00:00Z, deposit, 1, 2, 1.0\n";
+
+    let keyed = |input: &'static str| {
+        read_csv_keyed(
+            Box::new(input.as_bytes()),
+            &CsvDialect::default(),
+            &Column::Name("time".to_string()),
+        )
+    };
+    // Equal keys keep order of inputs
+    assert_eq!(
+        merged(vec![keyed(a), keyed(b)]),
+        vec![(0, 1), (1, 2), (0, 3)]
+    );
+}
+
+#[test]
+fn test_merge_errors() {
+    let a = "seq, type, client, tx, amount\n1, deposit, 1, 1, 1.0\n";
+    let unordered = "seq, type, client, tx, amount\n3, deposit, 1, 2, 1.0\n2, deposit, 1, 3, 1.0\n";
+
+    let results: Vec<_> = merge(vec![keyed(a), keyed(unordered)]).collect();
+    assert!(matches!(
+        results[..],
+        [
+            (0, Ok(_)),
+            (1, Ok(_)),
+            (1, Err(EngineError::InvalidMerge(_)))
+        ]
+    ));
+
+    let results: Vec<_> = merge(vec![keyed(a), keyed("type, client, tx, amount\n")]).collect();
+    assert!(matches!(
+        results[..],
+        [
+            (1, Err(EngineError::MissingColumn { line: 1, .. })),
+            (0, Ok(_))
+        ]
+    ));
+}
+
+#[test]
+fn test_merge_key_index_out_of_header() {
+    let results: Vec<_> = merge(vec![read_csv_keyed(
+        Box::new("seq, type, client, tx, amount\n1, deposit, 1, 1, 1.0\n".as_bytes()),
+        &CsvDialect::default(),
+        &Column::Index(9),
+    )])
+    .collect();
+
+    assert!(matches!(
+        &results[..],
+        [(0, Err(EngineError::InvalidDialect(message)))]
+            if message == "column index 9 is out of range, header has 5 columns"
+    ));
+}
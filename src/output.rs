@@ -0,0 +1,114 @@
+use std::io::{self, Write};
+
+use serde::Serialize;
+
+use crate::types::{Amount, ClientId, EngineError};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum OutputFormat {
+    Csv,
+    /// JSON array of client balances
+    Json,
+    /// One JSON client balance object per line
+    JsonLines,
+    /// Parquet file with amounts as `Decimal128`
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+impl OutputFormat {
+    pub(crate) fn parse(format: &str) -> Option<Self> {
+        match format {
+            "csv" => Some(OutputFormat::Csv),
+            "json" => Some(OutputFormat::Json),
+            "jsonl" => Some(OutputFormat::JsonLines),
+            #[cfg(feature = "parquet")]
+            "parquet" => Some(OutputFormat::Parquet),
+            _ => None,
+        }
+    }
+}
+
+/// Client row of the final report. Optional columns are present only when the feature is configured.
+#[derive(Debug, Serialize, PartialEq)]
+pub(crate) struct ClientBalance {
+    pub(crate) client: ClientId,
+    /// Amounts are serialized as strings to keep precision, same as on input.
+    #[serde(with = "rust_decimal::serde::str")]
+    pub(crate) available: Amount,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub(crate) held: Amount,
+    #[serde(with = "rust_decimal::serde::str")]
+    pub(crate) total: Amount,
+    pub(crate) locked: bool,
+    /// Number of deposits and withdrawals with open dispute, not part of CSV report
+    pub(crate) open_disputes: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) limit_breaches: Option<usize>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "rust_decimal::serde::str_option"
+    )]
+    pub(crate) fees: Option<Amount>,
+}
+
+pub(crate) fn print_balances(
+    balances: &[ClientBalance],
+    format: OutputFormat,
+) -> Result<(), EngineError> {
+    match format {
+        OutputFormat::Csv => write_csv(balances, &mut io::stdout().lock())?,
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(balances).expect("client balances are serializable")
+        ),
+        OutputFormat::JsonLines => {
+            for balance in balances {
+                println!(
+                    "{}",
+                    serde_json::to_string(balance).expect("client balance is serializable")
+                );
+            }
+        }
+        #[cfg(feature = "parquet")]
+        OutputFormat::Parquet => crate::columnar::write_parquet(balances, std::io::stdout())?,
+    }
+
+    Ok(())
+}
+
+/// Hand formatted to keep `, ` separators of the original report.
+pub(crate) fn write_csv(balances: &[ClientBalance], writer: &mut impl Write) -> io::Result<()> {
+    let with_breaches = balances
+        .first()
+        .is_some_and(|balance| balance.limit_breaches.is_some());
+    let with_fees = balances
+        .first()
+        .is_some_and(|balance| balance.fees.is_some());
+
+    write!(writer, "client, available, held, total, locked")?;
+    if with_breaches {
+        write!(writer, ", limit_breaches")?;
+    }
+    if with_fees {
+        write!(writer, ", fees")?;
+    }
+    writeln!(writer)?;
+
+    for balance in balances {
+        write!(
+            writer,
+            "{}, {}, {}, {}, {}",
+            balance.client, balance.available, balance.held, balance.total, balance.locked
+        )?;
+        if let Some(limit_breaches) = balance.limit_breaches {
+            write!(writer, ", {}", limit_breaches)?;
+        }
+        if let Some(fees) = balance.fees {
+            write!(writer, ", {}", fees)?;
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
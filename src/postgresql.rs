@@ -0,0 +1,159 @@
+use postgres::{Client, NoTls};
+
+use crate::{
+    snapshot::Snapshot,
+    store::{self, Changes, Rows, Store},
+    types::EngineError,
+};
+
+/// Engine state in PostgreSQL database, schema is migrated on connect.
+pub(crate) struct PostgresStore {
+    client: Client,
+    /// Number of ledger postings and flagged transactions in database
+    saved_postings: usize,
+    saved_flagged: usize,
+}
+
+impl PostgresStore {
+    pub(crate) fn connect(url: &str) -> Result<Self, EngineError> {
+        let mut client = Client::connect(url, NoTls)?;
+        migrate(&mut client)?;
+
+        Ok(Self {
+            client,
+            saved_postings: 0,
+            saved_flagged: 0,
+        })
+    }
+}
+
+fn migrate(client: &mut Client) -> Result<(), EngineError> {
+    client.batch_execute(store::CREATE_MIGRATIONS)?;
+    let version: i64 = client.query_one(store::SELECT_VERSION, &[])?.get(0);
+
+    for (index, migration) in store::MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let mut transaction = client.transaction()?;
+        transaction.batch_execute(migration)?;
+        transaction.execute(store::INSERT_VERSION, &[&(index as i64 + 1)])?;
+        transaction.commit()?;
+    }
+
+    Ok(())
+}
+
+impl Store for PostgresStore {
+    fn load(&mut self) -> Result<Option<Snapshot>, EngineError> {
+        let Some(row) = self.client.query_opt(store::SELECT_STATE, &[])? else {
+            return Ok(None);
+        };
+        let state: String = row.get(0);
+
+        let rows = Rows {
+            state,
+            clients: self
+                .client
+                .query(store::SELECT_CLIENTS, &[])?
+                .iter()
+                .map(|row| (row.get(0), row.get(1), row.get(2), row.get(3)))
+                .collect(),
+            transactions: self
+                .client
+                .query(store::SELECT_TRANSACTIONS, &[])?
+                .iter()
+                .map(|row| (row.get(0), row.get(1), row.get(2), row.get(3), row.get(4)))
+                .collect(),
+            postings: self
+                .client
+                .query(store::SELECT_POSTINGS, &[])?
+                .iter()
+                .map(|row| (row.get(0), row.get(1), row.get(2), row.get(3)))
+                .collect(),
+            client_states: self
+                .client
+                .query(store::SELECT_CLIENT_STATES, &[])?
+                .iter()
+                .map(|row| (row.get(0), row.get(1)))
+                .collect(),
+            flagged: self
+                .client
+                .query(store::SELECT_FLAGGED, &[])?
+                .iter()
+                .map(|row| (row.get(0), row.get(1), row.get(2)))
+                .collect(),
+        };
+
+        self.saved_postings = rows.postings.len();
+        self.saved_flagged = rows.flagged.len();
+        store::snapshot(rows).map(Some)
+    }
+
+    fn save(&mut self, changes: &Changes) -> Result<(), EngineError> {
+        let mut transaction = self.client.transaction()?;
+
+        for update in &changes.updates {
+            let client_id = i64::from(update.client_id);
+            if let Some(client) = update.client {
+                transaction.execute(
+                    store::UPSERT_CLIENT,
+                    &[
+                        &client_id,
+                        &client.available.to_string(),
+                        &client.held.to_string(),
+                        &client.locked,
+                    ],
+                )?;
+            }
+            if let Some((tx, stored)) = update.transaction {
+                transaction.execute(
+                    store::UPSERT_TRANSACTION,
+                    &[
+                        &client_id,
+                        &i64::from(tx),
+                        &store::name(&stored.tx_type),
+                        &stored.amount.to_string(),
+                        &store::name(&stored.dispute_state),
+                    ],
+                )?;
+            }
+            transaction.execute(
+                store::UPSERT_CLIENT_STATE,
+                &[&client_id, &store::client_state_json(&update.state)],
+            )?;
+        }
+        for (id, posting) in changes
+            .postings
+            .iter()
+            .enumerate()
+            .skip(self.saved_postings)
+        {
+            transaction.execute(
+                store::INSERT_POSTING,
+                &[
+                    &(id as i64),
+                    &i64::from(posting.tx),
+                    &posting.from.to_string(),
+                    &posting.to.to_string(),
+                    &posting.amount.to_string(),
+                ],
+            )?;
+        }
+        for (id, flagged) in changes.flagged.iter().enumerate().skip(self.saved_flagged) {
+            transaction.execute(
+                store::INSERT_FLAGGED,
+                &[
+                    &(id as i64),
+                    &i64::from(flagged.client),
+                    &i64::from(flagged.tx),
+                    &flagged.rule,
+                ],
+            )?;
+        }
+        transaction.execute(store::UPSERT_STATE, &[&store::state_json(changes.state)])?;
+
+        transaction.commit()?;
+        self.saved_postings = changes.postings.len();
+        self.saved_flagged = changes.flagged.len();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,230 @@
+use std::{fs, path::Path};
+
+use rustyline::{error::ReadlineError, DefaultEditor};
+
+use crate::{
+    dialect,
+    engine::Engine,
+    snapshot::{self, Snapshot},
+    store,
+    types::{ClientId, EngineError, TransactionOutcome},
+};
+
+const HELP: &str = "\
+deposit, 1, 1, 1.0   apply transaction in standard column order
+show client <id>     balances and stored transactions of client
+history <id>         transactions of client entered in this session
+undo [<count>]       revert the last transaction or given number of them
+checkpoint <name>    name the current state
+rollback <name>      revert transactions entered since checkpoint
+save <file>          write snapshot of engine state
+load <file>          replace engine state by snapshot
+help                 this help
+quit                 exit, also Ctrl-D
+";
+
+/// Transaction entered in session, each has undo step in engine journal.
+struct Entry {
+    line: String,
+    client: ClientId,
+    reply: String,
+}
+
+/// Interactive shell over engine, see [`Repl::execute`] for commands.
+pub(crate) struct Repl<'a> {
+    engine: &'a mut Engine,
+    entries: Vec<Entry>,
+    /// Number of the last line, used in parse errors
+    lines: u64,
+}
+
+/// Read commands from terminal until `quit` or end of input.
+pub(crate) fn run(engine: &mut Engine) -> Result<(), EngineError> {
+    let mut editor = DefaultEditor::new()?;
+    let mut repl = Repl::new(engine);
+    println!("Type transactions or commands, help for list of commands");
+
+    loop {
+        let line = match editor.readline("stte> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+        if matches!(line.trim(), "quit" | "exit") {
+            return Ok(());
+        }
+        if !line.trim().is_empty() {
+            editor.add_history_entry(line.as_str())?;
+        }
+        print!("{}", repl.execute(&line));
+    }
+}
+
+impl<'a> Repl<'a> {
+    pub(crate) fn new(engine: &'a mut Engine) -> Self {
+        Self {
+            engine,
+            entries: Vec::new(),
+            lines: 0,
+        }
+    }
+
+    /// Reply to one line terminated by newline, empty for empty line.
+    ///
+    /// Transaction is answered by its outcome followed by state of its client.
+    /// Undo relies on engine rollback, which has to be enabled with limit covering the session.
+    pub(crate) fn execute(&mut self, line: &str) -> String {
+        self.lines += 1;
+        let words: Vec<_> = line.split_whitespace().collect();
+
+        let reply = match words.as_slice() {
+            [] => Ok(String::new()),
+            ["help"] => Ok(HELP.to_string()),
+            ["show", "client", client_id] => self
+                .parse_client(client_id)
+                .map(|client_id| self.show_client(client_id)),
+            ["history", client_id] => self
+                .parse_client(client_id)
+                .map(|client_id| self.history(client_id)),
+            ["undo"] => self.undo(1),
+            ["undo", count] => match count.parse() {
+                Ok(count) => self.undo(count),
+                Err(_) => Ok(format!("error: invalid count {}\n", count)),
+            },
+            ["checkpoint", name] => self
+                .engine
+                .checkpoint(name)
+                .map(|()| format!("checkpoint {}\n", name)),
+            ["rollback", name] => self
+                .engine
+                .rollback_to(name)
+                .map(|count| self.undone(count)),
+            ["save", filename] => self.save(Path::new(filename)),
+            ["load", filename] => self.load(Path::new(filename)),
+            _ => self.transaction(line),
+        };
+        reply.unwrap_or_else(|err| format!("error: {}\n", err))
+    }
+
+    fn transaction(&mut self, line: &str) -> Result<String, EngineError> {
+        let transaction = dialect::parse_line(line, self.lines)?;
+        let client = transaction.client;
+
+        // Failed transaction can be undone as well
+        let mut reply = match self.engine.process_transaction(transaction) {
+            Ok(TransactionOutcome::Applied) => "applied\n".to_string(),
+            Ok(TransactionOutcome::Rejected(reason)) => format!("rejected {}\n", reason.name()),
+            Err(err) => format!("error: {}\n", err),
+        };
+        self.entries.push(Entry {
+            line: line.trim().to_string(),
+            client,
+            reply: reply.trim_end().to_string(),
+        });
+
+        reply.push_str(&self.show_client(client));
+        Ok(reply)
+    }
+
+    fn show_client(&self, client_id: ClientId) -> String {
+        let Some(client) = self.engine.client(client_id) else {
+            return format!("client {}: unknown\n", client_id);
+        };
+
+        let mut reply = format!(
+            "client {}: available {}, held {}, total {}, locked {}\n",
+            client_id,
+            client.available,
+            client.held,
+            client.available + client.held,
+            client.locked
+        );
+        let mut transactions: Vec<_> = client.transactions.iter().collect();
+        transactions.sort_by_key(|(tx, _)| **tx);
+        for (tx, stored) in transactions {
+            reply.push_str(&format!(
+                "  tx {}: {} {}, dispute {}\n",
+                tx,
+                store::name(&stored.tx_type),
+                stored.amount,
+                store::name(&stored.dispute_state)
+            ));
+        }
+        reply
+    }
+
+    fn history(&self, client_id: ClientId) -> String {
+        let reply: String = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.client == client_id)
+            .map(|(index, entry)| format!("{}: {} -> {}\n", index + 1, entry.line, entry.reply))
+            .collect();
+
+        match reply.is_empty() {
+            true => format!("no transactions of client {}\n", client_id),
+            false => reply,
+        }
+    }
+
+    fn undo(&mut self, count: usize) -> Result<String, EngineError> {
+        if self.entries.is_empty() {
+            return Ok("nothing to undo\n".to_string());
+        }
+        if count > self.entries.len() {
+            return Err(EngineError::Rollback(format!(
+                "only {} transactions were entered",
+                self.entries.len()
+            )));
+        }
+        self.engine.rollback_steps(count)?;
+        Ok(self.undone(count))
+    }
+
+    /// Remove `count` rolled back entries, reply lists them followed by state of their clients.
+    fn undone(&mut self, count: usize) -> String {
+        let entries = self.entries.split_off(self.entries.len() - count);
+        let mut reply = String::new();
+        let mut clients = Vec::new();
+        for entry in entries.iter().rev() {
+            reply.push_str(&format!("undone {}\n", entry.line));
+            if !clients.contains(&entry.client) {
+                clients.push(entry.client);
+            }
+        }
+        for client in clients {
+            reply.push_str(&self.show_client(client));
+        }
+        reply
+    }
+
+    fn save(&self, path: &Path) -> Result<String, EngineError> {
+        let content = serde_json::to_vec_pretty(&self.engine.snapshot())?;
+        snapshot::write_atomic(path, &content)?;
+        Ok(format!("saved {}\n", path.display()))
+    }
+
+    /// Loaded state cannot be undone, history of session is cleared.
+    fn load(&mut self, path: &Path) -> Result<String, EngineError> {
+        let snapshot: Snapshot = serde_json::from_slice(&fs::read(path)?)?;
+        self.engine.restore(snapshot)?;
+        self.entries.clear();
+        Ok(format!("loaded {}\n", path.display()))
+    }
+
+    fn parse_client(&self, client_id: &str) -> Result<ClientId, EngineError> {
+        client_id
+            .parse()
+            .map_err(|_| EngineError::NonNumericClient {
+                line: self.lines,
+                column: "client".to_string(),
+                value: client_id.to_string(),
+            })
+    }
+}
+
+#[cfg(test)]
+#[path = "repl.test.rs"]
+mod tests;
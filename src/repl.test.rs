@@ -0,0 +1,150 @@
+use crate::{engine::Engine, repl::Repl, testing::temp_path};
+
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_rollback_limit(usize::MAX);
+    engine
+}
+
+fn execute(repl: &mut Repl, lines: &[&str]) -> String {
+    lines.iter().map(|line| repl.execute(line)).collect()
+}
+
+#[test]
+fn test_transaction_shows_client() {
+    let mut engine = engine();
+    let mut repl = Repl::new(&mut engine);
+
+    assert_eq!(
+        execute(&mut repl, &["deposit, 3, 1, 10.0", "dispute, 3, 1,"]),
+        concat!(
+            "applied\n",
+            "client 3: available 10.0, held 0, total 10.0, locked false\n",
+            "  tx 1: deposit 10.0, dispute none\n",
+            "applied\n",
+            "client 3: available 0.0, held 10.0, total 10.0, locked false\n",
+            "  tx 1: deposit 10.0, dispute open\n",
+        )
+    );
+    assert_eq!(
+        execute(&mut repl, &["withdrawal, 3, 2, 1.0", "", "show client 4"]),
+        concat!(
+            "rejected insufficient_funds\n",
+            "client 3: available 0.0, held 10.0, total 10.0, locked false\n",
+            "  tx 1: deposit 10.0, dispute open\n",
+            "client 4: unknown\n",
+        )
+    );
+}
+
+#[test]
+fn test_history() {
+    let mut engine = engine();
+    let mut repl = Repl::new(&mut engine);
+    execute(
+        &mut repl,
+        &[
+            "deposit, 1, 1, 1.0",
+            "deposit, 2, 2, 1.0",
+            "withdrawal, 1, 3, 2.0",
+        ],
+    );
+
+    assert_eq!(
+        repl.execute("history 1"),
+        "1: deposit, 1, 1, 1.0 -> applied\n3: withdrawal, 1, 3, 2.0 -> rejected insufficient_funds\n"
+    );
+    assert_eq!(repl.execute("history 5"), "no transactions of client 5\n");
+}
+
+#[test]
+fn test_undo_restores_state() {
+    let mut engine = engine();
+    engine.allow_admin_transactions();
+    let mut repl = Repl::new(&mut engine);
+    execute(&mut repl, &["deposit, 1, 1, 5.0", "deposit, 1, 2, 1.0"]);
+    let before = repl.execute("show client 1");
+
+    execute(
+        &mut repl,
+        &["limit, 1, 3, 10.0", "dispute, 1, 1,", "chargeback, 1, 1,"],
+    );
+    assert_eq!(
+        execute(&mut repl, &["undo", "undo"]),
+        concat!(
+            "undone chargeback, 1, 1,\n",
+            "client 1: available 1.0, held 5.0, total 6.0, locked false\n",
+            "  tx 1: deposit 5.0, dispute open\n",
+            "  tx 2: deposit 1.0, dispute none\n",
+            "undone dispute, 1, 1,\n",
+            "client 1: available 6.0, held 0, total 6.0, locked false\n",
+            "  tx 1: deposit 5.0, dispute none\n",
+            "  tx 2: deposit 1.0, dispute none\n",
+        )
+    );
+    repl.execute("undo");
+    assert_eq!(repl.execute("show client 1"), before);
+
+    // Credit limit of undone limit transaction is gone
+    assert_eq!(
+        repl.execute("withdrawal, 1, 4, 7.0").lines().next(),
+        Some("rejected insufficient_funds")
+    );
+    execute(&mut repl, &["undo", "undo", "undo"]);
+    assert_eq!(repl.execute("undo"), "nothing to undo\n");
+    assert_eq!(repl.execute("show client 1"), "client 1: unknown\n");
+}
+
+#[test]
+fn test_checkpoint_and_rollback() {
+    let mut engine = engine();
+    let mut repl = Repl::new(&mut engine);
+    execute(&mut repl, &["deposit, 1, 1, 5.0", "checkpoint batch"]);
+
+    execute(
+        &mut repl,
+        &["deposit, 2, 2, 1.0", "deposit, 1, 3, 1.0", "dispute, 1, 1,"],
+    );
+    assert_eq!(
+        repl.execute("rollback batch"),
+        concat!(
+            "undone dispute, 1, 1,\n",
+            "undone deposit, 1, 3, 1.0\n",
+            "undone deposit, 2, 2, 1.0\n",
+            "client 1: available 5.0, held 0, total 5.0, locked false\n",
+            "  tx 1: deposit 5.0, dispute none\n",
+            "client 2: unknown\n",
+        )
+    );
+    assert_eq!(repl.execute("history 2"), "no transactions of client 2\n");
+
+    assert_eq!(
+        repl.execute("undo 2"),
+        "error: Rollback failed: only 1 transactions were entered\n"
+    );
+    assert_eq!(
+        repl.execute("undo 1"),
+        "undone deposit, 1, 1, 5.0\nclient 1: unknown\n"
+    );
+}
+
+#[test]
+fn test_save_and_load() {
+    let path = temp_path("repl.json");
+    let mut engine = engine();
+    let mut repl = Repl::new(&mut engine);
+    execute(&mut repl, &["deposit, 1, 1, 5.0", "dispute, 1, 1,"]);
+
+    assert_eq!(
+        repl.execute(&format!("save {}", path.display())),
+        format!("saved {}\n", path.display())
+    );
+    let saved = repl.execute("show client 1");
+    execute(&mut repl, &["resolve, 1, 1,", "deposit, 2, 2, 1.0"]);
+
+    repl.execute(&format!("load {}", path.display()));
+    assert_eq!(repl.execute("show client 1"), saved);
+    assert_eq!(repl.execute("show client 2"), "client 2: unknown\n");
+    assert_eq!(repl.execute("undo"), "nothing to undo\n");
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::types::{
+    Amount, Client, ClientId, DisputeState, EngineError, Transaction, TransactionType,
+};
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum RiskDecision {
+    Allow,
+    /// Transaction is not applied
+    Reject,
+    /// Transaction is applied and reported for review
+    Flag,
+    /// Account is locked after the transaction is processed
+    Lock,
+}
+
+/// Custom risk rule evaluated before transaction mutates client state.
+///
+/// Rules see only transactions of unlocked clients, admin transactions are not evaluated.
+pub(crate) trait RiskRule {
+    /// Name used in reject reasons and flag reports
+    fn name(&self) -> &'static str;
+
+    fn evaluate(&mut self, transaction: &Transaction, client: &Client) -> RiskDecision;
+
+    /// State kept between transactions, saved in engine snapshots. `Null` stands for the initial state.
+    fn state(&self) -> serde_json::Value {
+        serde_json::Value::Null
+    }
+
+    fn restore(&mut self, _state: serde_json::Value) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    /// Part of state concerning one client, kept to roll back transactions of the client and saved to database
+    fn client_state(&self, _client_id: ClientId) -> serde_json::Value {
+        serde_json::Value::Null
+    }
+
+    /// Restore state of one client returned by `client_state`, invalid state comes from a database row
+    /// and fails with [`EngineError::InvalidDatabase`].
+    fn restore_client(
+        &mut self,
+        _client_id: ClientId,
+        _state: serde_json::Value,
+    ) -> Result<(), EngineError> {
+        Ok(())
+    }
+}
+
+/// Enabling of built-in rules in engine config, e.g.:
+///
+/// ```toml
+/// [risk_rules]
+/// immediate_withdrawal = "flag"
+/// lock_on_open_disputes = 3
+/// ```
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct RiskRulesConfig {
+    /// Decision of [`ImmediateWithdrawalRule`], `"flag"` or `"reject"` make sense
+    pub(crate) immediate_withdrawal: Option<RiskDecision>,
+    pub(crate) lock_on_open_disputes: Option<usize>,
+}
+
+pub(crate) fn builtin_rules(config: &RiskRulesConfig) -> Vec<Box<dyn RiskRule>> {
+    let mut rules: Vec<Box<dyn RiskRule>> = Vec::new();
+
+    if let Some(decision) = config.immediate_withdrawal {
+        rules.push(Box::new(ImmediateWithdrawalRule::new(decision)));
+    }
+    if let Some(max_open_disputes) = config.lock_on_open_disputes {
+        rules.push(Box::new(OpenDisputesRule { max_open_disputes }));
+    }
+
+    rules
+}
+
+/// Matches withdrawal of at least the amount of deposit directly preceding it.
+pub(crate) struct ImmediateWithdrawalRule {
+    decision: RiskDecision,
+    /// Amount of deposit if it was the last transaction of client
+    last_deposit: HashMap<ClientId, Amount>,
+}
+
+impl ImmediateWithdrawalRule {
+    pub(crate) fn new(decision: RiskDecision) -> Self {
+        Self {
+            decision,
+            last_deposit: HashMap::new(),
+        }
+    }
+}
+
+impl RiskRule for ImmediateWithdrawalRule {
+    fn name(&self) -> &'static str {
+        "immediate_withdrawal"
+    }
+
+    fn evaluate(&mut self, transaction: &Transaction, _client: &Client) -> RiskDecision {
+        let last_deposit = self.last_deposit.remove(&transaction.client);
+
+        match (&transaction.tx_type, transaction.amount) {
+            (TransactionType::Deposit, Some(amount)) => {
+                self.last_deposit.insert(transaction.client, amount);
+                RiskDecision::Allow
+            }
+            (TransactionType::Withdrawal, Some(amount))
+                if last_deposit.is_some_and(|deposit| amount >= deposit) =>
+            {
+                self.decision
+            }
+            _ => RiskDecision::Allow,
+        }
+    }
+
+    fn state(&self) -> serde_json::Value {
+        serde_json::to_value(&self.last_deposit).expect("amounts are serializable")
+    }
+
+    fn restore(&mut self, state: serde_json::Value) -> Result<(), EngineError> {
+        self.last_deposit = serde_json::from_value::<Option<_>>(state)
+            .map_err(|err| EngineError::InvalidSnapshot(format!("{}: {}", self.name(), err)))?
+            .unwrap_or_default();
+        Ok(())
+    }
+
+    fn client_state(&self, client_id: ClientId) -> serde_json::Value {
+        serde_json::to_value(self.last_deposit.get(&client_id)).expect("amount is serializable")
+    }
+
+    fn restore_client(
+        &mut self,
+        client_id: ClientId,
+        state: serde_json::Value,
+    ) -> Result<(), EngineError> {
+        let state = serde_json::from_value(state).map_err(|err| {
+            EngineError::InvalidDatabase(format!(
+                "{} of client {}: {}",
+                self.name(),
+                client_id,
+                err
+            ))
+        })?;
+        match state {
+            Some(amount) => self.last_deposit.insert(client_id, amount),
+            None => self.last_deposit.remove(&client_id),
+        };
+        Ok(())
+    }
+}
+
+/// Locks account when dispute makes number of open disputes reach the limit.
+pub(crate) struct OpenDisputesRule {
+    pub(crate) max_open_disputes: usize,
+}
+
+impl RiskRule for OpenDisputesRule {
+    fn name(&self) -> &'static str {
+        "open_disputes"
+    }
+
+    fn evaluate(&mut self, transaction: &Transaction, client: &Client) -> RiskDecision {
+        if transaction.tx_type != TransactionType::Dispute {
+            return RiskDecision::Allow;
+        }
+
+        let opens_dispute = client
+            .transactions
+            .get(&transaction.tx)
+            .is_some_and(|stored| stored.dispute_state == DisputeState::None);
+        let open_disputes = client
+            .transactions
+            .values()
+            .filter(|stored| stored.dispute_state == DisputeState::Open)
+            .count();
+
+        if opens_dispute && open_disputes + 1 >= self.max_open_disputes {
+            RiskDecision::Lock
+        } else {
+            RiskDecision::Allow
+        }
+    }
+}
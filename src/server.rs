@@ -0,0 +1,247 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dialect::CsvDialect,
+    engine::Engine,
+    input::{self, InputFormat},
+    types::{ClientId, EngineError, RejectReason, Transaction, TransactionId, TransactionOutcome},
+};
+
+/// Outcome of single submitted transaction.
+#[derive(Debug, Serialize)]
+pub(crate) struct TransactionResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) client: Option<ClientId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) tx: Option<TransactionId>,
+    #[serde(flatten)]
+    pub(crate) outcome: Outcome,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub(crate) enum Outcome {
+    Applied,
+    Rejected {
+        reason: RejectReason,
+    },
+    /// Transaction could not be parsed or is invalid, e.g. missing amount
+    Error {
+        error: String,
+    },
+}
+
+impl TransactionResult {
+    fn error(err: EngineError) -> Self {
+        Self {
+            client: None,
+            tx: None,
+            outcome: Outcome::Error {
+                error: err.to_string(),
+            },
+        }
+    }
+}
+
+/// Body of `POST /rollback`, either number of steps or name of checkpoint.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum RollbackRequest {
+    Steps(usize),
+    Checkpoint(String),
+}
+
+/// JSON response of HTTP request.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Response {
+    pub(crate) status: u16,
+    pub(crate) body: String,
+}
+
+impl Response {
+    fn json<T: Serialize + ?Sized>(value: &T) -> Self {
+        Self {
+            status: 200,
+            body: serde_json::to_string(value).expect("response is serializable"),
+        }
+    }
+
+    fn error(status: u16, message: impl ToString) -> Self {
+        Self {
+            status,
+            body: serde_json::json!({ "error": message.to_string() }).to_string(),
+        }
+    }
+}
+
+/// Serve engine over HTTP until the process is stopped, requests are handled one by one.
+pub(crate) fn serve(engine: &mut Engine, address: &str) -> Result<(), EngineError> {
+    let server = tiny_http::Server::http(address).map_err(|err| {
+        EngineError::Io(std::io::Error::new(
+            std::io::ErrorKind::AddrNotAvailable,
+            err.to_string(),
+        ))
+    })?;
+    eprintln!("Listening on http://{}", address);
+
+    for mut request in server.incoming_requests() {
+        let content_type = request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv("Content-Type"))
+            .map(|header| header.value.to_string());
+        let mut body = Vec::new();
+        let response = match request.as_reader().read_to_end(&mut body) {
+            Ok(_) => handle(
+                engine,
+                request.method().as_str(),
+                request.url(),
+                content_type.as_deref(),
+                body,
+            ),
+            Err(err) => Response::error(400, err),
+        };
+
+        let header = tiny_http::Header::from_bytes("Content-Type", "application/json")
+            .expect("header is valid");
+        let response = tiny_http::Response::from_string(response.body)
+            .with_status_code(response.status)
+            .with_header(header);
+        if let Err(err) = request.respond(response) {
+            eprintln!("Error sending response: {}", err);
+        }
+    }
+
+    Ok(())
+}
+
+/// Route request to engine:
+/// - `POST /transactions` - single transaction or batch as JSON, or CSV with `Content-Type: text/csv`,
+/// - `GET /clients` - balances of all clients,
+/// - `GET /clients/{id}` - balance of single client,
+/// - `POST /checkpoints/{name}` - name the current state for rollback,
+/// - `POST /rollback` - revert `{"steps": n}` last transactions or those since `{"checkpoint": "name"}`.
+pub(crate) fn handle(
+    engine: &mut Engine,
+    method: &str,
+    url: &str,
+    content_type: Option<&str>,
+    body: Vec<u8>,
+) -> Response {
+    let path: Vec<_> = url
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+
+    match (method, &path[..]) {
+        ("POST", ["transactions"]) => {
+            let media_type = content_type
+                .and_then(|content_type| content_type.split(';').next())
+                .map(str::trim);
+            let format = match media_type {
+                None | Some("application/json") => InputFormat::Json,
+                Some("text/csv") => InputFormat::Csv,
+                Some(media_type) => {
+                    return Response::error(415, format!("unsupported content type {}", media_type))
+                }
+            };
+            match parse_body(format, body) {
+                Ok(transactions) => Response::json(&submit(engine, transactions)),
+                Err(err) => Response::error(400, err),
+            }
+        }
+        ("GET", ["clients"]) => Response::json(&engine.client_balances()),
+        ("GET", ["clients", client]) => {
+            let Ok(client) = client.parse::<ClientId>() else {
+                return Response::error(400, format!("invalid client id {}", client));
+            };
+            match engine.client_balance(client) {
+                Some(balance) => Response::json(&balance),
+                None => Response::error(404, format!("client {} not found", client)),
+            }
+        }
+        ("POST", ["checkpoints", name]) => match engine.checkpoint(name) {
+            Ok(()) => Response::json(&serde_json::json!({ "checkpoint": name })),
+            Err(err) => Response::error(409, err),
+        },
+        ("POST", ["rollback"]) => {
+            let result = match serde_json::from_slice(&body) {
+                Ok(RollbackRequest::Steps(steps)) => engine.rollback_steps(steps).map(|()| steps),
+                Ok(RollbackRequest::Checkpoint(name)) => engine.rollback_to(&name),
+                Err(err) => return Response::error(400, err),
+            };
+            match result {
+                Ok(steps) => Response::json(&serde_json::json!({ "rolled_back": steps })),
+                Err(err) => Response::error(409, err),
+            }
+        }
+        (
+            _,
+            ["transactions"] | ["clients"] | ["clients", _] | ["checkpoints", _] | ["rollback"],
+        ) => Response::error(405, "method not allowed"),
+        _ => Response::error(404, "not found"),
+    }
+}
+
+/// Parsed transactions, invalid records of batch are kept as errors to be reported in their place.
+///
+/// JSON body is single transaction object or array of them.
+fn parse_body(
+    format: InputFormat,
+    body: Vec<u8>,
+) -> Result<Vec<Result<Transaction, EngineError>>, EngineError> {
+    if format == InputFormat::Csv {
+        return Ok(input::read_transactions_from(
+            Box::new(std::io::Cursor::new(body)),
+            format,
+            &CsvDialect::default(),
+        )?
+        .collect());
+    }
+
+    // Element number of array body stands for line in errors
+    Ok(match serde_json::from_slice(&body)? {
+        serde_json::Value::Array(values) => values
+            .iter()
+            .zip(1..)
+            .map(|(value, number)| input::parse_json(value, number))
+            .collect(),
+        value => vec![input::parse_json(&value, 1)],
+    })
+}
+
+fn submit(
+    engine: &mut Engine,
+    transactions: Vec<Result<Transaction, EngineError>>,
+) -> Vec<TransactionResult> {
+    transactions
+        .into_iter()
+        .map(|transaction| {
+            let transaction = match transaction {
+                Ok(transaction) => transaction,
+                Err(err) => return TransactionResult::error(err),
+            };
+            let (client, tx) = (Some(transaction.client), Some(transaction.tx));
+
+            let outcome = match engine.process_transaction(transaction) {
+                Ok(TransactionOutcome::Applied) => Outcome::Applied,
+                Ok(TransactionOutcome::Rejected(reason)) => Outcome::Rejected { reason },
+                Err(err) => Outcome::Error {
+                    error: err.to_string(),
+                },
+            };
+            TransactionResult {
+                client,
+                tx,
+                outcome,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[path = "server.test.rs"]
+mod tests;
@@ -0,0 +1,172 @@
+use serde_json::{json, Value};
+
+use crate::{
+    engine::Engine,
+    server::{handle, Response},
+};
+
+fn request(
+    engine: &mut Engine,
+    method: &str,
+    url: &str,
+    content_type: Option<&str>,
+    body: &str,
+) -> (u16, Value) {
+    let Response { status, body } =
+        handle(engine, method, url, content_type, body.as_bytes().to_vec());
+    (status, serde_json::from_str(&body).unwrap())
+}
+
+#[test]
+fn test_submit_transactions() {
+    let mut engine = Engine::new();
+
+    let single = r#"{"type": "deposit", "client": 1, "tx": 1, "amount": "2.0"}"#;
+    assert_eq!(
+        request(&mut engine, "POST", "/transactions", None, single),
+        (200, json!([{"client": 1, "tx": 1, "outcome": "applied"}]))
+    );
+
+    let batch = r#"[
+        {"type": "withdrawal", "client": 1, "tx": 2, "amount": "5.0"},
+        {"type": "deposit", "client": 1, "tx": 3},
+        {"type": "depositt", "client": 1, "tx": 4, "amount": "1.0"},
+        {"type": "dispute", "client": 1, "tx": 1}
+    ]"#;
+    let (status, results) = request(
+        &mut engine,
+        "POST",
+        "/transactions",
+        Some("application/json"),
+        batch,
+    );
+    assert_eq!(status, 200);
+    assert_eq!(
+        results[0],
+        json!({"client": 1, "tx": 2, "outcome": "rejected", "reason": "insufficient_funds"})
+    );
+    assert_eq!(
+        results[1],
+        json!({"client": 1, "tx": 3, "outcome": "error", "error": "Missing amount field in transaction with id: 3"})
+    );
+    assert_eq!(results[2]["outcome"], "error");
+    assert_eq!(
+        results[3],
+        json!({"client": 1, "tx": 1, "outcome": "applied"})
+    );
+
+    let csv = "type, client, tx, amount\ndeposit, 2, 5, 1.5\ndeposit, two, 6, 1.0\n";
+    let (status, results) = request(
+        &mut engine,
+        "POST",
+        "/transactions",
+        Some("text/csv; charset=utf-8"),
+        csv,
+    );
+    assert_eq!(status, 200);
+    assert_eq!(
+        results,
+        json!([
+            {"client": 2, "tx": 5, "outcome": "applied"},
+            {"outcome": "error", "error": "Client id must be an integer from 0 to 65535, got \"two\" in line 3, column client"}
+        ])
+    );
+}
+
+#[test]
+fn test_query_clients() {
+    let mut engine = Engine::new();
+    let batch = r#"[
+        {"type": "deposit", "client": 2, "tx": 1, "amount": "2.0"},
+        {"type": "deposit", "client": 1, "tx": 2, "amount": "1.0"},
+        {"type": "dispute", "client": 1, "tx": 2}
+    ]"#;
+    request(&mut engine, "POST", "/transactions", None, batch);
+
+    assert_eq!(
+        request(&mut engine, "GET", "/clients/1", None, ""),
+        (
+            200,
+            json!({"client": 1, "available": "0.0", "held": "1.0", "total": "1.0", "locked": false, "open_disputes": 1})
+        )
+    );
+    let (status, clients) = request(&mut engine, "GET", "/clients", None, "");
+    assert_eq!(status, 200);
+    assert_eq!(clients.as_array().unwrap().len(), 2);
+    assert_eq!(clients[1]["client"], 2);
+}
+
+#[test]
+fn test_request_errors() {
+    let mut engine = Engine::new();
+
+    assert_eq!(request(&mut engine, "GET", "/clients/3", None, "").0, 404);
+    assert_eq!(request(&mut engine, "GET", "/clients/x", None, "").0, 400);
+    assert_eq!(request(&mut engine, "GET", "/accounts", None, "").0, 404);
+    assert_eq!(request(&mut engine, "DELETE", "/clients", None, "").0, 405);
+    assert_eq!(
+        request(&mut engine, "POST", "/transactions", None, "not json").0,
+        400
+    );
+    assert_eq!(
+        request(&mut engine, "POST", "/transactions", Some("text/xml"), "").0,
+        415
+    );
+}
+
+#[test]
+fn test_rollback() {
+    let mut engine = Engine::new();
+    assert_eq!(
+        request(&mut engine, "POST", "/checkpoints/batch", None, ""),
+        (
+            409,
+            json!({"error": "Rollback failed: rollback is not enabled"})
+        )
+    );
+
+    engine.set_rollback_limit(10);
+    let deposit = r#"{"type": "deposit", "client": 1, "tx": 1, "amount": "2.0"}"#;
+    request(&mut engine, "POST", "/transactions", None, deposit);
+    assert_eq!(
+        request(&mut engine, "POST", "/checkpoints/batch", None, ""),
+        (200, json!({"checkpoint": "batch"}))
+    );
+    let batch = r#"[
+        {"type": "deposit", "client": 1, "tx": 2, "amount": "1.0"},
+        {"type": "deposit", "client": 2, "tx": 3, "amount": "1.0"}
+    ]"#;
+    request(&mut engine, "POST", "/transactions", None, batch);
+
+    assert_eq!(
+        request(
+            &mut engine,
+            "POST",
+            "/rollback",
+            None,
+            r#"{"checkpoint": "batch"}"#
+        ),
+        (200, json!({"rolled_back": 2}))
+    );
+    assert_eq!(
+        request(&mut engine, "GET", "/clients/1", None, "").1["available"],
+        "2.0"
+    );
+    assert_eq!(request(&mut engine, "GET", "/clients/2", None, "").0, 404);
+
+    assert_eq!(
+        request(&mut engine, "POST", "/rollback", None, r#"{"steps": 2}"#),
+        (
+            409,
+            json!({"error": "Rollback failed: only 1 transactions can be rolled back"})
+        )
+    );
+    assert_eq!(
+        request(&mut engine, "POST", "/rollback", None, r#"{"steps": 1}"#),
+        (200, json!({"rolled_back": 1}))
+    );
+    assert_eq!(
+        request(&mut engine, "POST", "/rollback", None, r#"{"count": 1}"#).0,
+        400
+    );
+}
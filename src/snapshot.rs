@@ -0,0 +1,62 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ledger::Posting,
+    limits::WithdrawalHistory,
+    types::{Amount, Client, ClientId, TransactionId},
+};
+
+/// Complete state of engine, see [`Engine::snapshot`](crate::engine::Engine::snapshot).
+///
+/// Config is not included except credit limits changed by admin transactions,
+/// snapshot must be restored into engine created with the same config.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub(crate) struct Snapshot {
+    pub(crate) sequence: u64,
+    /// Sequence number of the last account change
+    pub(crate) changes: u64,
+    pub(crate) clients: HashMap<ClientId, Client>,
+    pub(crate) credit_limits: HashMap<ClientId, Amount>,
+    /// Ledger balances are recomputed from postings
+    pub(crate) postings: Vec<Posting>,
+    pub(crate) withdrawal_history: HashMap<ClientId, WithdrawalHistory>,
+    pub(crate) limit_breaches: HashMap<ClientId, usize>,
+    pub(crate) flagged: Vec<(ClientId, TransactionId, String)>,
+    pub(crate) locked_by_rule: HashMap<ClientId, String>,
+    /// State of each risk rule by its name
+    pub(crate) risk_rules: HashMap<String, serde_json::Value>,
+    /// Client states of risk rules by rule name restored over `risk_rules`, used by database stores
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub(crate) client_risk_rules: HashMap<ClientId, HashMap<String, serde_json::Value>>,
+}
+
+/// Replace content of file so that it is either old or new one after crash.
+///
+/// Content is written to temporary file next to `path`, synced to disk and renamed over `path`.
+pub(crate) fn write_atomic(path: &Path, content: &[u8]) -> io::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+
+    let mut file = File::create(&temporary)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    fs::rename(&temporary, path)?;
+
+    // Persist the rename itself
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    File::open(directory)?.sync_all()
+}
+
+#[cfg(test)]
+#[path = "snapshot.test.rs"]
+mod tests;
@@ -0,0 +1,82 @@
+use crate::{
+    config::EngineConfig,
+    engine::Engine,
+    snapshot::{write_atomic, Snapshot},
+    testing::{process, temp_path},
+    types::EngineError,
+};
+
+const CONFIG: &str = r#"
+[withdrawal_limits]
+max_count = 2
+window = 10
+
+[fees]
+deposit_percent = "1.0"
+
+[risk_rules]
+immediate_withdrawal = "flag"
+"#;
+
+#[test]
+fn test_restored_engine_continues_like_original() {
+    let mut original = Engine::with_config(toml::from_str(CONFIG).unwrap());
+    original.allow_admin_transactions();
+    process(
+        &mut original,
+        &[
+            "deposit, 1, 1, 100.0",
+            "limit, 1, 2, 50.0",
+            "withdrawal, 1, 3, 120.0",
+            "deposit, 2, 4, 10.0",
+            "dispute, 2, 4,",
+            "deposit, 1, 5, 5.0",
+        ],
+    );
+
+    let json = serde_json::to_string(&original.snapshot()).unwrap();
+    let snapshot: Snapshot = serde_json::from_str(&json).unwrap();
+    let mut restored = Engine::with_config(toml::from_str(CONFIG).unwrap());
+    restored.restore(snapshot).unwrap();
+    assert_eq!(restored.snapshot(), original.snapshot());
+
+    let rest = [
+        // Flagged only when the rule remembers the last deposit
+        "withdrawal, 1, 6, 5.0",
+        // Rejected by withdrawal count in window
+        "withdrawal, 1, 7, 1.0",
+        "resolve, 2, 4,",
+        "withdrawal, 1, 8, 1.0",
+    ];
+    process(&mut original, &rest);
+    process(&mut restored, &rest);
+
+    assert_eq!(restored.client_balances(), original.client_balances());
+    assert_eq!(restored.snapshot(), original.snapshot());
+    assert_eq!(restored.snapshot().flagged.len(), 2);
+    restored.verify_ledger().unwrap();
+}
+
+#[test]
+fn test_restore_with_different_risk_rules() {
+    let mut original = Engine::with_config(toml::from_str(CONFIG).unwrap());
+    process(&mut original, &["deposit, 1, 1, 1.0"]);
+
+    let mut restored = Engine::with_config(EngineConfig::default());
+    assert!(matches!(
+        restored.restore(original.snapshot()),
+        Err(EngineError::InvalidSnapshot(message)) if message == "unknown risk rule immediate_withdrawal"
+    ));
+}
+
+#[test]
+fn test_write_atomic() {
+    let path = temp_path("snapshot.json");
+
+    write_atomic(&path, b"first").unwrap();
+    write_atomic(&path, b"second").unwrap();
+
+    assert_eq!(std::fs::read(&path).unwrap(), b"second");
+    assert!(!path.with_extension("json.tmp").exists());
+    std::fs::remove_file(path).unwrap();
+}
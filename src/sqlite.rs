@@ -0,0 +1,163 @@
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::{
+    snapshot::Snapshot,
+    store::{self, Changes, Rows, Store},
+    types::EngineError,
+};
+
+/// Engine state in SQLite database file, schema is migrated on open.
+pub(crate) struct SqliteStore {
+    connection: Connection,
+    /// Number of ledger postings and flagged transactions in database
+    saved_postings: usize,
+    saved_flagged: usize,
+}
+
+impl SqliteStore {
+    pub(crate) fn open(path: &str) -> Result<Self, EngineError> {
+        let mut connection = Connection::open(path)?;
+        migrate(&mut connection)?;
+
+        Ok(Self {
+            connection,
+            saved_postings: 0,
+            saved_flagged: 0,
+        })
+    }
+
+    fn rows<T>(
+        &self,
+        sql: &str,
+        row: impl FnMut(&rusqlite::Row) -> rusqlite::Result<T>,
+    ) -> Result<Vec<T>, EngineError> {
+        Ok(self
+            .connection
+            .prepare(sql)?
+            .query_map([], row)?
+            .collect::<Result<_, _>>()?)
+    }
+}
+
+fn migrate(connection: &mut Connection) -> Result<(), EngineError> {
+    connection.execute_batch(store::CREATE_MIGRATIONS)?;
+    let version: i64 = connection.query_row(store::SELECT_VERSION, [], |row| row.get(0))?;
+
+    for (index, migration) in store::MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(migration)?;
+        transaction.execute(store::INSERT_VERSION, [index as i64 + 1])?;
+        transaction.commit()?;
+    }
+
+    Ok(())
+}
+
+impl Store for SqliteStore {
+    fn load(&mut self) -> Result<Option<Snapshot>, EngineError> {
+        let state: Option<String> = self
+            .connection
+            .query_row(store::SELECT_STATE, [], |row| row.get(0))
+            .optional()?;
+        let Some(state) = state else {
+            return Ok(None);
+        };
+
+        let rows = Rows {
+            state,
+            clients: self.rows(store::SELECT_CLIENTS, |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?,
+            transactions: self.rows(store::SELECT_TRANSACTIONS, |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                ))
+            })?,
+            postings: self.rows(store::SELECT_POSTINGS, |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?,
+            client_states: self.rows(store::SELECT_CLIENT_STATES, |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?,
+            flagged: self.rows(store::SELECT_FLAGGED, |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })?,
+        };
+
+        self.saved_postings = rows.postings.len();
+        self.saved_flagged = rows.flagged.len();
+        store::snapshot(rows).map(Some)
+    }
+
+    fn save(&mut self, changes: &Changes) -> Result<(), EngineError> {
+        let transaction = self.connection.transaction()?;
+
+        for update in &changes.updates {
+            if let Some(client) = update.client {
+                transaction.execute(
+                    store::UPSERT_CLIENT,
+                    params![
+                        update.client_id,
+                        client.available.to_string(),
+                        client.held.to_string(),
+                        client.locked
+                    ],
+                )?;
+            }
+            if let Some((tx, stored)) = update.transaction {
+                transaction.execute(
+                    store::UPSERT_TRANSACTION,
+                    params![
+                        update.client_id,
+                        tx,
+                        store::name(&stored.tx_type),
+                        stored.amount.to_string(),
+                        store::name(&stored.dispute_state)
+                    ],
+                )?;
+            }
+            transaction.execute(
+                store::UPSERT_CLIENT_STATE,
+                params![update.client_id, store::client_state_json(&update.state)],
+            )?;
+        }
+        for (id, posting) in changes
+            .postings
+            .iter()
+            .enumerate()
+            .skip(self.saved_postings)
+        {
+            transaction.execute(
+                store::INSERT_POSTING,
+                params![
+                    id as i64,
+                    posting.tx,
+                    posting.from.to_string(),
+                    posting.to.to_string(),
+                    posting.amount.to_string()
+                ],
+            )?;
+        }
+        for (id, flagged) in changes.flagged.iter().enumerate().skip(self.saved_flagged) {
+            transaction.execute(
+                store::INSERT_FLAGGED,
+                params![id as i64, flagged.client, flagged.tx, flagged.rule],
+            )?;
+        }
+        transaction.execute(store::UPSERT_STATE, [store::state_json(changes.state)])?;
+
+        transaction.commit()?;
+        self.saved_postings = changes.postings.len();
+        self.saved_flagged = changes.flagged.len();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[path = "sqlite.test.rs"]
+mod tests;
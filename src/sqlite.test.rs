@@ -0,0 +1,304 @@
+use std::{path::Path, sync::mpsc};
+
+use rusqlite::Connection;
+use rust_decimal_macros::dec;
+
+use crate::{
+    dialect,
+    engine::Engine,
+    snapshot::Snapshot,
+    sqlite::SqliteStore,
+    store::{self, Store},
+    testing::{process, temp_path},
+    types::EngineError,
+};
+
+const CONFIG: &str = r#"
+[withdrawal_limits]
+max_count = 2
+window = 10
+
+[risk_rules]
+immediate_withdrawal = "flag"
+"#;
+
+fn open_engine(path: &Path) -> Engine {
+    open_engine_with(Engine::new(), path)
+}
+
+fn open_engine_with(mut engine: Engine, path: &Path) -> Engine {
+    engine
+        .open_store(Box::new(SqliteStore::open(path.to_str().unwrap()).unwrap()))
+        .unwrap();
+    engine
+}
+
+fn configured_engine(path: &Path) -> Engine {
+    let mut engine = Engine::with_config(toml::from_str(CONFIG).unwrap());
+    engine.allow_admin_transactions();
+    open_engine_with(engine, path)
+}
+
+fn saved_state(path: &Path) -> Snapshot {
+    let state: String = Connection::open(path)
+        .unwrap()
+        .query_row("SELECT state FROM engine_state", [], |row| row.get(0))
+        .unwrap();
+    serde_json::from_str(&state).unwrap()
+}
+
+/// Client 1 is flagged once and breaches withdrawal count, client 2 has credit limit and last deposit
+const CONFIGURED_LINES: [&str; 7] = [
+    "deposit, 1, 1, 100.0",
+    "limit, 2, 2, 50.0",
+    "withdrawal, 1, 3, 100.0",
+    "deposit, 1, 4, 10.0",
+    "withdrawal, 1, 5, 1.0",
+    "withdrawal, 1, 6, 1.0",
+    "deposit, 2, 7, 5.0",
+];
+
+fn count(path: &Path, table: &str) -> i64 {
+    Connection::open(path)
+        .unwrap()
+        .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+            row.get(0)
+        })
+        .unwrap()
+}
+
+#[test]
+fn test_restart_resumes_from_database() {
+    let path = temp_path("sqlite-restart.db");
+
+    let mut engine = open_engine(&path);
+    process(
+        &mut engine,
+        &[
+            "deposit, 1, 1, 10.0",
+            "deposit, 2, 2, 5.0",
+            "withdrawal, 1, 3, 2.5",
+            "dispute, 2, 2,",
+        ],
+    );
+    let snapshot = engine.snapshot();
+    drop(engine);
+
+    let mut engine = open_engine(&path);
+    assert_eq!(engine.snapshot(), snapshot);
+
+    // Dispute of transaction processed before restart is resolved after it
+    process(&mut engine, &["resolve, 2, 2,", "deposit, 1, 4, 1.0"]);
+    let client = engine.client_balance(2).unwrap();
+    assert_eq!((client.available, client.held), (dec!(5.0), dec!(0)));
+    let snapshot = engine.snapshot();
+    drop(engine);
+
+    assert_eq!(open_engine(&path).snapshot(), snapshot);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_repeated_tx_id_is_saved_like_in_memory() {
+    let path = temp_path("sqlite-repeated.db");
+
+    // Engine keeps the last deposit of repeated id, database has to keep the same
+    let mut engine = open_engine(&path);
+    process(
+        &mut engine,
+        &["deposit, 1, 1, 100", "deposit, 1, 1, 5", "dispute, 1, 1,"],
+    );
+    drop(engine);
+
+    let mut engine = open_engine(&path);
+    process(&mut engine, &["resolve, 1, 1,"]);
+    let client = engine.client_balance(1).unwrap();
+    assert_eq!((client.available, client.held), (dec!(105), dec!(0)));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_failed_save_reverts_transaction() {
+    let path = temp_path("sqlite-failed-save.db");
+
+    let mut engine = open_engine(&path);
+    process(&mut engine, &["deposit, 1, 1, 10.0"]);
+    let snapshot = engine.snapshot();
+    let (sender, changes) = mpsc::channel();
+    engine.subscribe(Box::new(sender));
+
+    Connection::open(&path)
+        .unwrap()
+        .execute("DROP TABLE postings", [])
+        .unwrap();
+    let transaction = dialect::parse_line("deposit, 2, 2, 5.0", 1).unwrap();
+    assert!(matches!(
+        engine.process_transaction(transaction),
+        Err(EngineError::Sqlite(_))
+    ));
+
+    assert_eq!(engine.snapshot(), snapshot);
+    assert!(changes.try_recv().is_err());
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_client_state_is_saved_in_rows() {
+    let path = temp_path("sqlite-client-state.db");
+
+    let mut engine = configured_engine(&path);
+    process(&mut engine, &CONFIGURED_LINES);
+    let snapshot = engine.snapshot();
+    assert_eq!(snapshot.flagged.len(), 1);
+    assert_eq!(snapshot.limit_breaches[&1], 1);
+    drop(engine);
+
+    // Engine state row does not grow with clients
+    let state = saved_state(&path);
+    assert!(state.withdrawal_history.is_empty() && state.flagged.is_empty());
+    assert_eq!(
+        state.risk_rules["immediate_withdrawal"],
+        serde_json::Value::Null
+    );
+    assert_eq!(count(&path, "client_state"), 2);
+    assert_eq!(count(&path, "flagged"), 1);
+
+    let mut engine = configured_engine(&path);
+    assert_eq!(engine.snapshot(), snapshot);
+    // Flagged only when the rule remembers the last deposit of client 2
+    process(&mut engine, &["withdrawal, 2, 8, 5.0"]);
+    assert_eq!(engine.snapshot().flagged.len(), 2);
+    drop(engine);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_corrupted_risk_rule_state_of_client() {
+    let path = temp_path("sqlite-corrupted-client-state.db");
+
+    let mut engine = configured_engine(&path);
+    process(&mut engine, &CONFIGURED_LINES);
+    drop(engine);
+    Connection::open(&path)
+        .unwrap()
+        .execute(
+            "UPDATE client_state SET state = $1 WHERE client = 2",
+            [r#"{"risk_rules": {"immediate_withdrawal": "abc"}}"#],
+        )
+        .unwrap();
+
+    let mut engine = Engine::with_config(toml::from_str(CONFIG).unwrap());
+    let err = engine
+        .open_store(Box::new(SqliteStore::open(path.to_str().unwrap()).unwrap()))
+        .unwrap_err();
+    assert!(
+        matches!(&err, EngineError::InvalidDatabase(message) if message.starts_with("immediate_withdrawal of client 2")),
+        "{:?}",
+        err
+    );
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_state_of_schema_version_1_is_moved_to_rows() {
+    let path = temp_path("sqlite-version-1.db");
+
+    let mut engine = configured_engine(&path);
+    process(&mut engine, &CONFIGURED_LINES);
+    let snapshot = engine.snapshot();
+    drop(engine);
+
+    // Version 1 saved whole state without clients and postings as JSON
+    let mut legacy: Snapshot =
+        serde_json::from_str(&serde_json::to_string(&snapshot).unwrap()).unwrap();
+    legacy.clients.clear();
+    legacy.postings.clear();
+    let connection = Connection::open(&path).unwrap();
+    connection
+        .execute_batch("DELETE FROM client_state; DELETE FROM flagged;")
+        .unwrap();
+    connection
+        .execute(
+            "UPDATE engine_state SET state = $1",
+            [serde_json::to_string(&legacy).unwrap()],
+        )
+        .unwrap();
+    drop(connection);
+
+    assert_eq!(configured_engine(&path).snapshot(), snapshot);
+    assert!(saved_state(&path).withdrawal_history.is_empty());
+    assert_eq!(configured_engine(&path).snapshot(), snapshot);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_postings_are_saved_once() {
+    let path = temp_path("sqlite-postings.db");
+
+    let mut engine = open_engine(&path);
+    process(
+        &mut engine,
+        &["deposit, 1, 1, 10.0", "withdrawal, 1, 2, 4.0"],
+    );
+    drop(engine);
+    let mut engine = open_engine(&path);
+    process(&mut engine, &["deposit, 1, 3, 1.0"]);
+
+    assert_eq!(
+        count(&path, "postings"),
+        engine.snapshot().postings.len() as i64
+    );
+    assert_eq!(count(&path, "transactions"), 3);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_migrations_are_applied_once() {
+    let path = temp_path("sqlite-migrations.db");
+
+    let mut store = SqliteStore::open(path.to_str().unwrap()).unwrap();
+    assert_eq!(store.load().unwrap(), None);
+    drop(store);
+    SqliteStore::open(path.to_str().unwrap()).unwrap();
+
+    assert_eq!(
+        count(&path, "schema_migrations"),
+        store::MIGRATIONS.len() as i64
+    );
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_open_with_prefix() {
+    let path = temp_path("sqlite-prefix.db");
+
+    let mut store = store::open(&format!("sqlite:{}", path.display())).unwrap();
+    assert_eq!(store.load().unwrap(), None);
+    assert!(path.exists());
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_batch_is_saved_on_commit() {
+    let path = temp_path("sqlite-batch.db");
+
+    let mut engine = open_engine(&path);
+    process(&mut engine, &["deposit, 1, 1, 10.0"]);
+    engine.begin().unwrap();
+    process(
+        &mut engine,
+        &["deposit, 2, 2, 5.0", "withdrawal, 1, 3, 4.0"],
+    );
+    assert_eq!(count(&path, "transactions"), 1);
+    engine.rollback().unwrap();
+
+    engine.begin().unwrap();
+    process(&mut engine, &["deposit, 2, 4, 5.0", "dispute, 1, 1,"]);
+    engine.commit().unwrap();
+    let snapshot = engine.snapshot();
+    drop(engine);
+
+    assert_eq!(open_engine(&path).snapshot(), snapshot);
+    assert_eq!(count(&path, "postings"), snapshot.postings.len() as i64);
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,102 @@
+use std::{collections::BTreeMap, fmt};
+
+use crate::{
+    engine::Engine,
+    input::{self, InputOptions},
+    store,
+    types::{Amount, EngineError, TransactionOutcome},
+};
+
+/// Counts of processed transactions and totals of client accounts after them.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Stats {
+    /// Read transactions by type name, including those which failed to process
+    pub(crate) transactions: BTreeMap<String, usize>,
+    pub(crate) applied: usize,
+    /// Rejected transactions by reason name
+    pub(crate) rejected: BTreeMap<String, usize>,
+    /// Records which could not be read or processed
+    pub(crate) invalid: usize,
+    pub(crate) clients: usize,
+    pub(crate) locked: usize,
+    pub(crate) open_disputes: usize,
+    pub(crate) available: Amount,
+    pub(crate) held: Amount,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "transactions: {}",
+            self.transactions.values().sum::<usize>()
+        )?;
+        for (tx_type, count) in &self.transactions {
+            writeln!(f, "  {}: {}", tx_type, count)?;
+        }
+        writeln!(f, "applied: {}", self.applied)?;
+        writeln!(f, "rejected: {}", self.rejected.values().sum::<usize>())?;
+        for (reason, count) in &self.rejected {
+            writeln!(f, "  {}: {}", reason, count)?;
+        }
+        writeln!(f, "invalid records: {}", self.invalid)?;
+        writeln!(f, "clients: {}", self.clients)?;
+        writeln!(f, "locked clients: {}", self.locked)?;
+        writeln!(f, "open disputes: {}", self.open_disputes)?;
+        writeln!(f, "available: {}", self.available)?;
+        writeln!(f, "held: {}", self.held)?;
+        writeln!(f, "total: {}", self.available + self.held)
+    }
+}
+
+/// Process inputs as one stream skipping invalid records, which are only counted.
+///
+/// IO errors and violated invariants stop processing.
+pub(crate) fn collect(
+    engine: &mut Engine,
+    filenames: &[String],
+    options: &InputOptions,
+) -> Result<Stats, EngineError> {
+    let mut stats = Stats::default();
+
+    for (_, result) in input::read_inputs(filenames, options) {
+        let outcome = result.and_then(|transaction| {
+            *stats
+                .transactions
+                .entry(store::name(&transaction.tx_type))
+                .or_default() += 1;
+            engine.process_transaction(transaction)
+        });
+
+        match outcome {
+            Ok(TransactionOutcome::Applied) => stats.applied += 1,
+            Ok(TransactionOutcome::Rejected(reason)) => {
+                *stats.rejected.entry(reason.name()).or_default() += 1
+            }
+            Err(err) if is_fatal(&err) => return Err(err),
+            Err(_) => stats.invalid += 1,
+        }
+    }
+    engine.check_invariants_at_end()?;
+
+    for balance in engine.client_balances() {
+        stats.clients += 1;
+        stats.locked += usize::from(balance.locked);
+        stats.open_disputes += balance.open_disputes;
+        stats.available += balance.available;
+        stats.held += balance.held;
+    }
+    Ok(stats)
+}
+
+fn is_fatal(err: &EngineError) -> bool {
+    match err {
+        EngineError::InvalidInput(err) => err.is_io_error(),
+        EngineError::Io(_) | EngineError::InvariantViolated(_) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+#[path = "stats.test.rs"]
+mod tests;
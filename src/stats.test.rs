@@ -0,0 +1,59 @@
+use std::collections::BTreeMap;
+
+use rust_decimal_macros::dec;
+
+use crate::{
+    engine::Engine,
+    input::InputOptions,
+    stats::{collect, Stats},
+    types::EngineError,
+};
+
+fn counts(counts: &[(&str, usize)]) -> BTreeMap<String, usize> {
+    counts
+        .iter()
+        .map(|(name, count)| (name.to_string(), *count))
+        .collect()
+}
+
+#[test]
+fn test_stats_count_invalid_records_and_continue() {
+    let mut engine = Engine::new();
+    let filenames = vec!["data/input-validate1.csv".to_string()];
+
+    let stats = collect(&mut engine, &filenames, &InputOptions::default()).unwrap();
+
+    assert_eq!(
+        stats,
+        Stats {
+            transactions: counts(&[
+                ("chargeback", 1),
+                ("deposit", 3),
+                ("dispute", 1),
+                ("withdrawal", 2)
+            ]),
+            applied: 4,
+            rejected: counts(&[("unknown_transaction", 1)]),
+            invalid: 4,
+            clients: 2,
+            locked: 0,
+            open_disputes: 1,
+            available: dec!(2.0),
+            held: dec!(2.0),
+        }
+    );
+    assert!(stats
+        .to_string()
+        .contains("rejected: 1\n  unknown_transaction: 1\n"));
+    assert!(stats.to_string().ends_with("held: 2.0\ntotal: 4.0\n"));
+}
+
+#[test]
+fn test_stats_of_missing_file_fail() {
+    let mut engine = Engine::new();
+    let filenames = vec!["data/missing.csv".to_string()];
+
+    let result = collect(&mut engine, &filenames, &InputOptions::default());
+
+    assert!(matches!(result, Err(EngineError::Io(_))));
+}
@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+
+use rust_decimal::Decimal;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+#[cfg(feature = "postgres")]
+use crate::postgresql::PostgresStore;
+use crate::{
+    engine::FlaggedTransaction,
+    ledger::Posting,
+    limits::WithdrawalHistory,
+    snapshot::Snapshot,
+    sqlite::SqliteStore,
+    types::{Amount, Client, ClientId, EngineError, StoredTransaction, TransactionId},
+};
+
+/// Changes made by one processed transaction or committed batch.
+pub(crate) struct Changes<'a> {
+    pub(crate) updates: Vec<Update<'a>>,
+    /// All postings of ledger, store saves those it has not saved yet
+    pub(crate) postings: &'a [Posting],
+    /// All flagged transactions, store saves those it has not saved yet
+    pub(crate) flagged: &'a [FlaggedTransaction],
+    /// Sequence numbers of engine, see [`Engine::stored_state`](crate::engine::Engine)
+    pub(crate) state: &'a Snapshot,
+}
+
+/// Client touched by processed transaction.
+pub(crate) struct Update<'a> {
+    pub(crate) client_id: ClientId,
+    /// `None` when the transaction did not reach the client, e.g. limit
+    pub(crate) client: Option<&'a Client>,
+    /// Deposit or withdrawal of the client with its id
+    pub(crate) transaction: Option<(TransactionId, &'a StoredTransaction)>,
+    pub(crate) state: ClientState,
+}
+
+/// Engine state of one client besides balances and stored transactions, saved as JSON in `client_state` table.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub(crate) struct ClientState {
+    /// `None` when client has no config entry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) credit_limit: Option<Amount>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) withdrawal_history: Option<WithdrawalHistory>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) limit_breaches: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) locked_by_rule: Option<String>,
+    /// Client state of risk rules by rule name, rules without state of the client are left out
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub(crate) risk_rules: HashMap<String, serde_json::Value>,
+}
+
+/// Database keeping engine state across restarts.
+pub(crate) trait Store {
+    /// State saved by previous runs, `None` for new database.
+    fn load(&mut self) -> Result<Option<Snapshot>, EngineError>;
+
+    /// Save changes in one database transaction.
+    fn save(&mut self, changes: &Changes) -> Result<(), EngineError>;
+}
+
+/// Open SQLite database file, optionally prefixed by `sqlite:`, or PostgreSQL database given by `postgres://` URL.
+pub(crate) fn open(url: &str) -> Result<Box<dyn Store>, EngineError> {
+    if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+        #[cfg(feature = "postgres")]
+        return Ok(Box::new(PostgresStore::connect(url)?));
+        #[cfg(not(feature = "postgres"))]
+        return Err(EngineError::InvalidConfig(
+            "PostgreSQL database requires build with postgres feature".to_string(),
+        ));
+    }
+
+    Ok(Box::new(SqliteStore::open(
+        url.strip_prefix("sqlite:").unwrap_or(url),
+    )?))
+}
+
+/// Schema changes in order of versions starting from 1, applied versions are kept in `schema_migrations`.
+///
+/// Types and statements are common for SQLite and PostgreSQL, amounts are decimal strings to keep precision.
+pub(crate) const MIGRATIONS: &[&str] = &[
+    "
+    CREATE TABLE clients (
+        client BIGINT PRIMARY KEY,
+        available TEXT NOT NULL,
+        held TEXT NOT NULL,
+        locked BOOLEAN NOT NULL
+    );
+    CREATE TABLE transactions (
+        client BIGINT NOT NULL,
+        tx BIGINT NOT NULL,
+        type TEXT NOT NULL,
+        amount TEXT NOT NULL,
+        dispute_state TEXT NOT NULL,
+        PRIMARY KEY (client, tx)
+    );
+    CREATE TABLE postings (
+        id BIGINT PRIMARY KEY,
+        tx BIGINT NOT NULL,
+        from_account TEXT NOT NULL,
+        to_account TEXT NOT NULL,
+        amount TEXT NOT NULL
+    );
+    CREATE TABLE engine_state (
+        id BIGINT PRIMARY KEY,
+        state TEXT NOT NULL
+    );
+",
+    "
+    CREATE TABLE client_state (
+        client BIGINT PRIMARY KEY,
+        state TEXT NOT NULL
+    );
+    CREATE TABLE flagged (
+        id BIGINT PRIMARY KEY,
+        client BIGINT NOT NULL,
+        tx BIGINT NOT NULL,
+        rule TEXT NOT NULL
+    );
+",
+];
+
+pub(crate) const CREATE_MIGRATIONS: &str =
+    "CREATE TABLE IF NOT EXISTS schema_migrations (version BIGINT PRIMARY KEY)";
+pub(crate) const SELECT_VERSION: &str = "SELECT COALESCE(MAX(version), 0) FROM schema_migrations";
+pub(crate) const INSERT_VERSION: &str = "INSERT INTO schema_migrations (version) VALUES ($1)";
+
+// SQLite accepts `$n` parameters as well
+pub(crate) const UPSERT_CLIENT: &str = "
+    INSERT INTO clients (client, available, held, locked) VALUES ($1, $2, $3, $4)
+    ON CONFLICT (client) DO UPDATE SET available = $2, held = $3, locked = $4";
+pub(crate) const UPSERT_TRANSACTION: &str = "
+    INSERT INTO transactions (client, tx, type, amount, dispute_state) VALUES ($1, $2, $3, $4, $5)
+    ON CONFLICT (client, tx) DO UPDATE SET type = $3, amount = $4, dispute_state = $5";
+pub(crate) const INSERT_POSTING: &str = "
+    INSERT INTO postings (id, tx, from_account, to_account, amount) VALUES ($1, $2, $3, $4, $5)";
+pub(crate) const UPSERT_CLIENT_STATE: &str = "
+    INSERT INTO client_state (client, state) VALUES ($1, $2)
+    ON CONFLICT (client) DO UPDATE SET state = $2";
+pub(crate) const INSERT_FLAGGED: &str =
+    "INSERT INTO flagged (id, client, tx, rule) VALUES ($1, $2, $3, $4)";
+pub(crate) const UPSERT_STATE: &str = "
+    INSERT INTO engine_state (id, state) VALUES (1, $1)
+    ON CONFLICT (id) DO UPDATE SET state = $1";
+pub(crate) const SELECT_STATE: &str = "SELECT state FROM engine_state WHERE id = 1";
+pub(crate) const SELECT_CLIENTS: &str = "SELECT client, available, held, locked FROM clients";
+pub(crate) const SELECT_TRANSACTIONS: &str =
+    "SELECT client, tx, type, amount, dispute_state FROM transactions";
+pub(crate) const SELECT_POSTINGS: &str =
+    "SELECT tx, from_account, to_account, amount FROM postings ORDER BY id";
+pub(crate) const SELECT_CLIENT_STATES: &str = "SELECT client, state FROM client_state";
+pub(crate) const SELECT_FLAGGED: &str = "SELECT client, tx, rule FROM flagged ORDER BY id";
+
+/// Row of `clients` table.
+pub(crate) type ClientRow = (i64, String, String, bool);
+/// Row of `transactions` table.
+pub(crate) type TransactionRow = (i64, i64, String, String, String);
+/// Row of `postings` table without id.
+pub(crate) type PostingRow = (i64, String, String, String);
+/// Row of `client_state` table.
+pub(crate) type ClientStateRow = (i64, String);
+/// Row of `flagged` table without id.
+pub(crate) type FlaggedRow = (i64, i64, String);
+
+/// All rows of tables loaded by store.
+#[derive(Default)]
+pub(crate) struct Rows {
+    /// JSON of `engine_state`
+    pub(crate) state: String,
+    pub(crate) clients: Vec<ClientRow>,
+    pub(crate) transactions: Vec<TransactionRow>,
+    pub(crate) postings: Vec<PostingRow>,
+    pub(crate) client_states: Vec<ClientStateRow>,
+    pub(crate) flagged: Vec<FlaggedRow>,
+}
+
+/// Name of unit variant as serialized by serde, e.g. `deposit`.
+pub(crate) fn name<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => panic!("value is not unit variant"),
+    }
+}
+
+pub(crate) fn state_json(state: &Snapshot) -> String {
+    serde_json::to_string(state).expect("state is serializable")
+}
+
+pub(crate) fn client_state_json(state: &ClientState) -> String {
+    serde_json::to_string(state).expect("state is serializable")
+}
+
+/// Snapshot of all loaded rows.
+///
+/// State of clients saved in `engine_state` by databases of schema version 1 is overridden by `client_state` rows.
+pub(crate) fn snapshot(rows: Rows) -> Result<Snapshot, EngineError> {
+    let mut snapshot: Snapshot = serde_json::from_str(&rows.state).map_err(invalid)?;
+
+    snapshot.clients = HashMap::new();
+    for (client_id, available, held, locked) in rows.clients {
+        snapshot.clients.insert(
+            number(client_id)?,
+            Client {
+                available: decimal(&available)?,
+                held: decimal(&held)?,
+                locked,
+                transactions: HashMap::new(),
+            },
+        );
+    }
+
+    for (client_id, tx, tx_type, amount, dispute_state) in rows.transactions {
+        let client = snapshot
+            .clients
+            .get_mut(&number(client_id)?)
+            .ok_or_else(|| invalid(format!("transaction {} of unknown client", tx)))?;
+        client.transactions.insert(
+            number(tx)?,
+            StoredTransaction {
+                tx_type: parse_name(&tx_type)?,
+                amount: decimal(&amount)?,
+                dispute_state: parse_name(&dispute_state)?,
+            },
+        );
+    }
+
+    snapshot.postings = rows
+        .postings
+        .into_iter()
+        .map(|(tx, from, to, amount)| {
+            Ok(Posting {
+                tx: number(tx)?,
+                from: from.parse().map_err(invalid)?,
+                to: to.parse().map_err(invalid)?,
+                amount: decimal(&amount)?,
+            })
+        })
+        .collect::<Result<_, EngineError>>()?;
+
+    for (client_id, state) in rows.client_states {
+        let client_id = number(client_id)?;
+        let state: ClientState = serde_json::from_str(&state).map_err(invalid)?;
+        set_entry(&mut snapshot.credit_limits, client_id, state.credit_limit);
+        set_entry(
+            &mut snapshot.withdrawal_history,
+            client_id,
+            state.withdrawal_history,
+        );
+        set_entry(
+            &mut snapshot.limit_breaches,
+            client_id,
+            state.limit_breaches,
+        );
+        set_entry(
+            &mut snapshot.locked_by_rule,
+            client_id,
+            state.locked_by_rule,
+        );
+        snapshot
+            .client_risk_rules
+            .insert(client_id, state.risk_rules);
+    }
+    for (client_id, tx, rule) in rows.flagged {
+        snapshot
+            .flagged
+            .push((number(client_id)?, number(tx)?, rule));
+    }
+
+    Ok(snapshot)
+}
+
+fn set_entry<V>(map: &mut HashMap<ClientId, V>, client_id: ClientId, value: Option<V>) {
+    match value {
+        Some(value) => map.insert(client_id, value),
+        None => map.remove(&client_id),
+    };
+}
+
+fn parse_name<T: DeserializeOwned>(name: &str) -> Result<T, EngineError> {
+    serde_json::from_value(serde_json::Value::String(name.to_string())).map_err(invalid)
+}
+
+fn decimal(text: &str) -> Result<Amount, EngineError> {
+    text.parse::<Decimal>()
+        .map_err(|_| invalid(format!("invalid amount {}", text)))
+}
+
+fn number<T: TryFrom<i64>>(value: i64) -> Result<T, EngineError> {
+    T::try_from(value).map_err(|_| invalid(format!("number {} out of range", value)))
+}
+
+fn invalid(err: impl ToString) -> EngineError {
+    EngineError::InvalidDatabase(err.to_string())
+}
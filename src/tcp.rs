@@ -0,0 +1,121 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{self, Sender},
+    thread,
+};
+
+use crate::{
+    dialect,
+    engine::Engine,
+    output,
+    types::{EngineError, TransactionOutcome},
+};
+
+/// Line received by connection, answered through `reply`.
+struct Request {
+    number: u64,
+    line: String,
+    reply: Sender<String>,
+}
+
+/// Accept CSV lines from any number of TCP connections and apply them one by one.
+///
+/// Protocol, one request per line:
+/// - `deposit, 1, 1, 1.0` - transaction in standard column order, answered `ack <tx>`, `nack <tx> <reason>`
+///   for rejected transaction or `nack <error>` for invalid line,
+/// - `snapshot` - CSV report of client balances terminated by `end` line,
+/// - header line, empty lines and lines starting with `#` are ignored.
+pub(crate) fn listen(engine: &mut Engine, address: &str) -> Result<(), EngineError> {
+    let listener = TcpListener::bind(address)?;
+    eprintln!("Listening on {}", listener.local_addr()?);
+
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let sender = sender.clone();
+                    thread::spawn(move || {
+                        if let Err(err) = connection(stream, sender) {
+                            eprintln!("Connection error: {}", err);
+                        }
+                    });
+                }
+                Err(err) => eprintln!("Error accepting connection: {}", err),
+            }
+        }
+    });
+
+    // Engine stays in this thread, connections only exchange lines with it
+    for request in receiver {
+        if let Some(reply) = handle_line(engine, request.number, &request.line) {
+            // Connection may be closed already
+            let _ = request.reply.send(reply);
+        }
+    }
+
+    Ok(())
+}
+
+fn connection(stream: TcpStream, requests: Sender<Request>) -> std::io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let (reply, replies) = mpsc::channel();
+
+    for (index, line) in BufReader::new(stream).lines().enumerate() {
+        let line = line?;
+        if is_ignored(&line) {
+            continue;
+        }
+
+        let request = Request {
+            number: index as u64 + 1,
+            line,
+            reply: reply.clone(),
+        };
+        if requests.send(request).is_err() {
+            break;
+        }
+        if let Ok(reply) = replies.recv() {
+            writer.write_all(reply.as_bytes())?;
+        }
+    }
+
+    Ok(())
+}
+
+fn is_ignored(line: &str) -> bool {
+    let line = line.trim();
+    line.is_empty() || line.starts_with('#') || line.starts_with("type")
+}
+
+/// Reply to line number `number` of connection, terminated by newline, `None` for ignored lines.
+pub(crate) fn handle_line(engine: &mut Engine, number: u64, line: &str) -> Option<String> {
+    if is_ignored(line) {
+        return None;
+    }
+    if line.trim() == "snapshot" {
+        let mut report = Vec::new();
+        output::write_csv(&engine.client_balances(), &mut report).expect("writing to vector");
+        report.extend_from_slice(b"end\n");
+        return Some(String::from_utf8(report).expect("report is UTF-8"));
+    }
+
+    let transaction = match dialect::parse_line(line, number) {
+        Ok(transaction) => transaction,
+        Err(err) => return Some(format!("nack {}\n", err)),
+    };
+    let tx = transaction.tx;
+
+    Some(match engine.process_transaction(transaction) {
+        Ok(TransactionOutcome::Applied) => format!("ack {}\n", tx),
+        Ok(TransactionOutcome::Rejected(reason)) => {
+            format!("nack {} {}\n", tx, reason.name())
+        }
+        Err(err) => format!("nack {} {}\n", tx, err),
+    })
+}
+
+#[cfg(test)]
+#[path = "tcp.test.rs"]
+mod tests;
@@ -0,0 +1,79 @@
+use std::{
+    io::{Read, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    sync::mpsc,
+    thread,
+};
+
+use crate::{
+    engine::Engine,
+    tcp::{connection, handle_line},
+};
+
+#[test]
+fn test_handle_line() {
+    let mut engine = Engine::new();
+
+    assert_eq!(
+        handle_line(&mut engine, 1, "type, client, tx, amount"),
+        None
+    );
+    assert_eq!(handle_line(&mut engine, 2, "# comment"), None);
+    assert_eq!(handle_line(&mut engine, 3, ""), None);
+    assert_eq!(
+        handle_line(&mut engine, 4, "deposit, 1, 1, 2.0").as_deref(),
+        Some("ack 1\n")
+    );
+    assert_eq!(
+        handle_line(&mut engine, 5, "withdrawal, 1, 2, 5.0").as_deref(),
+        Some("nack 2 insufficient_funds\n")
+    );
+    assert_eq!(
+        handle_line(&mut engine, 6, "deposit, 1, 3").as_deref(),
+        Some("nack 3 Missing amount field in transaction with id: 3\n")
+    );
+    assert_eq!(
+        handle_line(&mut engine, 7, "deposit, one, 4, 1.0").as_deref(),
+        Some("nack Client id must be an integer from 0 to 65535, got \"one\" in line 7, column client\n")
+    );
+    assert_eq!(
+        handle_line(&mut engine, 8, "snapshot").as_deref(),
+        Some("client, available, held, total, locked\n1, 2.0, 0, 2.0, false\nend\n")
+    );
+}
+
+#[test]
+fn test_connection() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        connection(stream, sender).unwrap();
+    });
+    let client = thread::spawn(move || {
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream
+            .write_all(
+                b"type, client, tx, amount\ndeposit, 1, 1, 2.0\nwithdrawal, 1, 2, 1.5\nsnapshot\n",
+            )
+            .unwrap();
+        stream.shutdown(Shutdown::Write).unwrap();
+        let mut replies = String::new();
+        stream.read_to_string(&mut replies).unwrap();
+        replies
+    });
+
+    // Requests end when the connection is closed
+    let mut engine = Engine::new();
+    for request in receiver {
+        let reply = handle_line(&mut engine, request.number, &request.line).unwrap();
+        request.reply.send(reply).unwrap();
+    }
+
+    assert_eq!(
+        client.join().unwrap(),
+        "ack 1\nack 2\nclient, available, held, total, locked\n1, 0.5, 0, 0.5, false\nend\n"
+    );
+}
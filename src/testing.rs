@@ -0,0 +1,20 @@
+//! Helpers shared by tests of multiple modules.
+
+use std::path::PathBuf;
+
+use crate::{dialect, engine::Engine};
+
+/// Process CSV lines without header, numbered from 1, every one has to be valid.
+pub(crate) fn process(engine: &mut Engine, lines: &[&str]) {
+    for (index, line) in lines.iter().enumerate() {
+        let transaction = dialect::parse_line(line, index as u64 + 1).unwrap();
+        engine.process_transaction(transaction).unwrap();
+    }
+}
+
+/// Path of file `name` in temporary directory unique to the test process, left over file is removed.
+pub(crate) fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("stte-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_file(&path);
+    path
+}
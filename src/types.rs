@@ -1,10 +1,10 @@
 use std::collections::HashMap;
 
 use rust_decimal::Decimal;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub(crate) enum TransactionType {
     Deposit,
@@ -12,13 +12,16 @@ pub(crate) enum TransactionType {
     Dispute,
     Resolve,
     Chargeback,
+    /// Admin transaction setting client's credit limit to `amount`
+    Limit,
 }
 
 pub(crate) type ClientId = u16;
-type TransactionId = u32;
-type Amount = Decimal;
+pub(crate) type TransactionId = u32;
+pub(crate) type Amount = Decimal;
 
-#[derive(Deserialize)]
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
 pub(crate) struct Transaction {
     /// Using `tx_type` because `type` is reserved word.
     #[serde(alias = "type")]
@@ -26,7 +29,8 @@ pub(crate) struct Transaction {
     pub(crate) client: ClientId,
     pub(crate) tx: TransactionId,
     /// Hint serde to use string instead of float for amount deserialization to avoid rounding errors.
-    #[serde(with = "rust_decimal::serde::str_option")]
+    /// Default allows missing field in JSON input.
+    #[serde(default, with = "rust_decimal::serde::str_option")]
     pub(crate) amount: Option<Amount>,
 }
 
@@ -38,9 +42,61 @@ impl Transaction {
         }
         Ok(amount)
     }
+
+    /// Credit limit of `Limit` admin transaction, zero is allowed to remove the limit.
+    pub(crate) fn get_limit(&self) -> Result<Amount, EngineError> {
+        let limit = self.amount.ok_or(EngineError::AmountMissing(self.tx))?;
+        if limit < Decimal::ZERO {
+            return Err(EngineError::AmountNegative(limit));
+        }
+        Ok(limit)
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum RejectReason {
+    AccountLocked,
+    /// Withdrawal amount exceeds available amount of client without credit limit
+    InsufficientFunds,
+    /// Withdrawal amount exceeds available amount increased by client's credit limit
+    CreditLimitExceeded,
+    /// Single withdrawal is over `max_amount` limit
+    WithdrawalAmountExceeded,
+    /// Sum of withdrawals in window would exceed `max_total` limit
+    WithdrawalTotalExceeded,
+    /// Number of withdrawals in window would exceed `max_count` limit
+    WithdrawalCountExceeded,
+    /// Rejected by risk rule with given name
+    RiskRule(&'static str),
+    /// Disputed transaction does not exist for the client
+    UnknownTransaction,
+    /// Disputed transaction is not in state required by dispute/resolve/chargeback
+    InvalidDisputeState,
+}
+
+impl RejectReason {
+    /// Snake case name used by service interfaces, e.g. `insufficient_funds` or `risk_rule:<name>`.
+    pub(crate) fn name(&self) -> String {
+        match self {
+            RejectReason::RiskRule(rule) => format!("risk_rule:{}", rule),
+            reason => serde_json::to_value(reason)
+                .ok()
+                .and_then(|value| value.as_str().map(str::to_string))
+                .unwrap_or_default(),
+        }
+    }
 }
 
+/// Result of processing a valid transaction. Rejected transactions leave client state untouched.
 #[derive(Debug, PartialEq)]
+pub(crate) enum TransactionOutcome {
+    Applied,
+    Rejected(RejectReason),
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub(crate) enum DisputeState {
     /// Initial state / set on resolve
     None,
@@ -51,7 +107,7 @@ pub(crate) enum DisputeState {
 }
 
 /// Structure for storing transaction for potential disputes.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Deserialize, PartialEq, Serialize)]
 pub(crate) struct StoredTransaction {
     pub(crate) tx_type: TransactionType,
     pub(crate) amount: Amount,
@@ -71,7 +127,7 @@ impl StoredTransaction {
 }
 
 /// Client == Account, as stated in requirements: "The client has a single asset account."
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Default, Clone, Deserialize, PartialEq, Serialize)]
 pub(crate) struct Client {
     pub(crate) available: Amount,
     pub(crate) held: Amount,
@@ -83,8 +139,122 @@ pub(crate) struct Client {
 pub enum EngineError {
     #[error("Error parsing CSV input: {0}")]
     InvalidInput(#[from] csv::Error),
+    /// `value` is the header name or the extra field of data row
+    #[error("Unknown column {column} with value {value:?} in line {line}")]
+    UnknownColumn {
+        line: u64,
+        column: String,
+        value: String,
+    },
+    /// `value` is the whole line lacking the column
+    #[error("Missing column {column} in line {line}, got {value:?}")]
+    MissingColumn {
+        line: u64,
+        column: String,
+        value: String,
+    },
+    #[error("Unknown transaction type {value:?} in line {line}, column {column}")]
+    BadTypeToken {
+        line: u64,
+        column: String,
+        value: String,
+    },
+    #[error("Client id must be an integer from 0 to 65535, got {value:?} in line {line}, column {column}")]
+    NonNumericClient {
+        line: u64,
+        column: String,
+        value: String,
+    },
+    #[error("Transaction id must be an integer from 0 to 4294967295, got {value:?} in line {line}, column {column}")]
+    NonIntegerTx {
+        line: u64,
+        column: String,
+        value: String,
+    },
+    #[error("Invalid decimal amount {value:?} in line {line}, column {column}")]
+    BadDecimal {
+        line: u64,
+        column: String,
+        value: String,
+    },
+    #[error("Invalid CSV dialect: {0}")]
+    InvalidDialect(String),
+    #[error("Invalid column mapping: {0}")]
+    InvalidMapping(String),
+    #[error("Error parsing JSON input: {0}")]
+    InvalidJsonInput(#[from] serde_json::Error),
+    /// Row of JSON Lines input, `line` counts blank lines too
+    #[error("Error parsing JSON input in line {line}: {message}")]
+    InvalidJsonLine { line: u64, message: String },
+    /// Element of JSON array input numbered from 1, `line` is where it starts
+    #[error("Error parsing JSON input in element {element}, line {line}: {message}")]
+    InvalidJsonElement {
+        element: u64,
+        line: u64,
+        message: String,
+    },
+    #[cfg(feature = "arrow")]
+    #[error("Error processing Arrow data: {0}")]
+    ArrowData(#[from] arrow::error::ArrowError),
+    #[cfg(feature = "parquet")]
+    #[error("Error processing Parquet data: {0}")]
+    ParquetData(#[from] parquet::errors::ParquetError),
+    #[cfg(feature = "grpc")]
+    #[error("Invalid gRPC message: {0}")]
+    InvalidMessage(String),
+    #[cfg(feature = "grpc")]
+    #[error("gRPC server error: {0}")]
+    Grpc(#[from] tonic::transport::Error),
+    #[cfg(feature = "kafka")]
+    #[error("Error consuming Kafka topic: {0}")]
+    Kafka(#[from] rdkafka::error::KafkaError),
+    #[error("Invalid snapshot: {0}")]
+    InvalidSnapshot(String),
+    #[error("Database error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[cfg(feature = "postgres")]
+    #[error("Database error: {0}")]
+    Postgres(#[from] postgres::Error),
+    #[error("Invalid database content: {0}")]
+    InvalidDatabase(String),
+    #[error("Terminal error: {0}")]
+    Readline(#[from] rustyline::error::ReadlineError),
     #[error("Missing amount field in transaction with id: {0}")]
     AmountMissing(TransactionId),
     #[error("Amount must be positive: {0}")]
     AmountNotPositive(Decimal),
+    #[error("Amount must not be negative: {0}")]
+    AmountNegative(Decimal),
+    #[error("Admin transaction {0} is not allowed, it is accepted only by process --admin")]
+    AdminTransaction(TransactionId),
+    #[error("Error reading file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Error parsing config: {0}")]
+    ConfigParse(#[from] toml::de::Error),
+    #[error("Invalid config: {0}")]
+    InvalidConfig(String),
+    /// Arguments accepted by clap but not valid together or in this build
+    #[error("Invalid arguments: {0}")]
+    Usage(String),
+    #[error("Ledger does not balance: {0}")]
+    LedgerImbalance(String),
+    #[error("Invalid merge of inputs: {0}")]
+    InvalidMerge(String),
+    #[error("{filename}: {source}")]
+    InFile {
+        filename: String,
+        source: Box<EngineError>,
+    },
+    #[error("Validation found {0} problems")]
+    ValidationFailed(usize),
+    #[error("Invariant violated: {0}")]
+    InvariantViolated(String),
+    #[error("Rollback failed: {0}")]
+    Rollback(String),
+    #[error("Invalid batch: {0}")]
+    Batch(String),
+    #[error("{0} inputs were rolled back")]
+    RolledBack(usize),
+    #[error("Balances of inputs have {0} differences")]
+    BalancesDiffer(usize),
 }
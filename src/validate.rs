@@ -0,0 +1,118 @@
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    input::{self, InputOptions},
+    types::{ClientId, EngineError, Transaction, TransactionId, TransactionType},
+};
+
+/// Problem found by [`validate`] without processing transactions.
+#[derive(Debug)]
+pub(crate) enum Problem {
+    /// Record could not be parsed or its amount is invalid
+    Invalid(EngineError),
+    DuplicateTransaction(TransactionId),
+    /// Dispute, resolve or chargeback of transaction not seen before for the client
+    UnknownTransaction(TransactionId),
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::Invalid(err) => write!(f, "{}", err),
+            Problem::DuplicateTransaction(tx) => {
+                write!(f, "duplicate transaction id {}", tx)
+            }
+            Problem::UnknownTransaction(tx) => {
+                write!(f, "reference to unknown transaction id {}", tx)
+            }
+        }
+    }
+}
+
+/// Result of validation, problems are numbered by record starting from 1.
+#[derive(Debug, Default)]
+pub(crate) struct Report {
+    pub(crate) records: usize,
+    pub(crate) problems: Vec<(usize, Problem)>,
+}
+
+/// Parse all transactions of input and collect every problem instead of stopping on the first one.
+pub(crate) fn validate(filename: &str, options: &InputOptions) -> Report {
+    let mut report = Report::default();
+
+    let transactions = match input::read_transactions(filename, options) {
+        Ok(transactions) => transactions,
+        Err(err) => {
+            report.problems.push((0, Problem::Invalid(err)));
+            return report;
+        }
+    };
+
+    let mut validator = Validator::default();
+    for result in transactions {
+        report.records += 1;
+
+        let problem = match result {
+            Ok(transaction) => validator.check(&transaction),
+            Err(err) => {
+                // Reading can not continue after IO error
+                let fatal = match &err {
+                    EngineError::InvalidInput(err) => err.is_io_error(),
+                    EngineError::Io(_) => true,
+                    _ => false,
+                };
+                report
+                    .problems
+                    .push((report.records, Problem::Invalid(err)));
+                if fatal {
+                    break;
+                }
+                continue;
+            }
+        };
+        if let Some(problem) = problem {
+            report.problems.push((report.records, problem));
+        }
+    }
+
+    report
+}
+
+/// Checks of transactions which do not need client balances.
+#[derive(Debug, Default)]
+struct Validator {
+    /// Client of each deposit and withdrawal
+    transactions: HashMap<TransactionId, ClientId>,
+}
+
+impl Validator {
+    fn check(&mut self, transaction: &Transaction) -> Option<Problem> {
+        match transaction.tx_type {
+            TransactionType::Deposit | TransactionType::Withdrawal => {
+                if let Err(err) = transaction.get_amount() {
+                    return Some(Problem::Invalid(err));
+                }
+                if self.transactions.contains_key(&transaction.tx) {
+                    return Some(Problem::DuplicateTransaction(transaction.tx));
+                }
+                self.transactions.insert(transaction.tx, transaction.client);
+            }
+            TransactionType::Dispute | TransactionType::Resolve | TransactionType::Chargeback => {
+                if self.transactions.get(&transaction.tx) != Some(&transaction.client) {
+                    return Some(Problem::UnknownTransaction(transaction.tx));
+                }
+            }
+            TransactionType::Limit => {
+                if let Err(err) = transaction.get_limit() {
+                    return Some(Problem::Invalid(err));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+#[path = "validate.test.rs"]
+mod tests;
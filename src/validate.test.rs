@@ -0,0 +1,94 @@
+use crate::{
+    input::InputOptions,
+    types::EngineError,
+    validate::{validate, Problem},
+};
+
+#[test]
+fn test_validate_reports_all_problems() {
+    let report = validate("data/input-validate1.csv", &InputOptions::default());
+
+    assert_eq!(report.records, 9);
+    let problems: Vec<_> = report
+        .problems
+        .iter()
+        .map(|(record, problem)| (*record, problem.to_string()))
+        .collect();
+    assert_eq!(
+        problems,
+        vec![
+            (2, "duplicate transaction id 1".to_string()),
+            (3, "Missing amount field in transaction with id: 2".to_string()),
+            (
+                4,
+                "Client id must be an integer from 0 to 65535, got \"one\" in line 5, column client"
+                    .to_string()
+            ),
+            (5, "reference to unknown transaction id 1".to_string()),
+            (6, "Amount must be positive: -1.0".to_string()),
+            (7, "reference to unknown transaction id 9".to_string()),
+            (
+                8,
+                "Unknown transaction type \"deposittt\" in line 9, column type".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_validate_valid_input() {
+    let report = validate("data/input-flow1.csv", &InputOptions::default());
+    assert_eq!(report.records, 13);
+    assert!(report.problems.is_empty());
+}
+
+#[test]
+fn test_validate_unreadable_input() {
+    let report = validate("data/input-invalid1.csv", &InputOptions::default());
+    assert_eq!(report.records, 0);
+    assert!(matches!(
+        report.problems[..],
+        [(0, Problem::Invalid(EngineError::UnknownColumn { .. }))]
+    ));
+}
+
+#[test]
+fn test_validate_reports_all_json_problems() {
+    for filename in ["data/input-validate2.jsonl", "data/input-validate2.json"] {
+        let report = validate(filename, &InputOptions::default());
+
+        assert_eq!(report.records, 5, "{}", filename);
+        let records: Vec<_> = report.problems.iter().map(|(record, _)| *record).collect();
+        assert_eq!(records, [2, 4], "{}", filename);
+    }
+
+    let report = validate("data/input-validate2.jsonl", &InputOptions::default());
+    assert!(matches!(
+        report.problems[..],
+        [
+            (
+                _,
+                Problem::Invalid(EngineError::InvalidJsonLine { line: 2, .. })
+            ),
+            (
+                _,
+                Problem::Invalid(EngineError::BadTypeToken { line: 4, .. })
+            ),
+        ]
+    ));
+
+    let report = validate("data/input-validate2.json", &InputOptions::default());
+    assert!(matches!(
+        report.problems[..],
+        [
+            (
+                _,
+                Problem::Invalid(EngineError::NonNumericClient { line: 3, .. })
+            ),
+            (
+                _,
+                Problem::Invalid(EngineError::BadTypeToken { line: 5, .. })
+            ),
+        ]
+    ));
+}